@@ -1,10 +1,24 @@
-use crate::dex::{CompactMethod, Method};
-use crate::manifest::Manifest;
+use crate::archive::{ArchiveEntry, Embedded};
+use crate::dex::{CallGraph, CompactMethod, IntentSend, Method};
+use crate::icc::IccEdge;
+use crate::manifest::{
+    value::{ResourceRef, TextValue},
+    Manifest,
+};
+use crate::network_security::NetworkSecurityConfig;
+use crate::resources::{value::ResValue, ResolvedValue, ResourceTable};
+use crate::signing::{Signatures, Verification};
+use crate::{errors::ApkParseError, xml};
 
 use serde::Serialize;
+use std::{
+    collections::HashMap,
+    io::{Read, Seek},
+};
+use zip::{result::ZipError, ZipArchive};
 
 /// Represents an APK (Android Package) with metadata and methods.
-#[derive(Debug, Serialize)]
+#[derive(Debug, Default, Serialize)]
 pub struct Apk {
     #[serde(rename = "man")]
     pub manifest: Option<Manifest>,
@@ -17,7 +31,40 @@ pub struct Apk {
     #[serde(rename = "mth")]
     pub methods: Vec<Method>,
 
-    pub files: Vec<String>,
+    /// Call graph of `methods`, including the synthetic callback edges
+    #[serde(skip)]
+    pub call_graph: CallGraph,
+
+    /// Intents sent from `methods` whose target could be recovered
+    #[serde(skip)]
+    pub intents: Vec<IntentSend>,
+
+    /// `intents` linked to the components of the manifest
+    pub icc: Vec<IccEdge>,
+
+    /// Compiled resources (`resources.arsc`), `None` if missing or malformed
+    #[serde(skip)]
+    pub resources: Option<ResourceTable>,
+
+    /// Configuration referenced by `android:networkSecurityConfig`, if any
+    #[serde(rename = "nsc")]
+    pub network_security_config: Option<NetworkSecurityConfig>,
+
+    /// Signers of the v1, v2, v3 and v3.1 signature schemes
+    #[serde(rename = "sig")]
+    pub signatures: Signatures,
+
+    /// Verdict of each signature scheme, `Failed` for tampered or repackaged APKs
+    #[serde(rename = "ver")]
+    pub verification: Verification,
+
+    /// Entries of the archive with their metadata
+    pub files: Vec<ArchiveEntry>,
+
+    /// APKs, JARs and DEX files shipped inside the archive (e.g. under `assets/`),
+    /// analysed on their own
+    #[serde(rename = "emb")]
+    pub embedded: Vec<Embedded>,
 }
 
 impl Apk {
@@ -28,6 +75,63 @@ impl Apk {
     pub fn to_compact(self) -> CompactApk {
         self.into()
     }
+
+    /// Values of a resource in every configuration, see `ResourceTable::resolve`
+    pub fn resolve(&self, reference: &ResourceRef) -> Vec<ResolvedValue<'_>> {
+        self.resources
+            .as_ref()
+            .map(|r| r.resolve(reference))
+            .unwrap_or_default()
+    }
+
+    /// Name of the app, `android:label` of the application resolved in the default configuration
+    pub fn label(&self) -> Option<String> {
+        match self.manifest.as_ref()?.application.label.as_ref()? {
+            TextValue::Literal(label) => Some(label.clone()),
+            TextValue::Reference(reference) => {
+                match self.resources.as_ref()?.resolve_default(reference)? {
+                    ResValue::String(s) => Some(s.clone()),
+                    _ => None,
+                }
+            }
+        }
+    }
+
+    /// Decodes a binary XML entry of the APK (e.g. `res/xml/file_paths.xml`),
+    /// resolving its resource references with `resources`.
+    ///
+    /// ### Arguments
+    /// * `apk`: The archive this `Apk` was parsed from.
+    /// * `path`: Path of the entry in the archive, see `files`.
+    pub fn read_xml<R: Read + Seek>(
+        &self,
+        apk: R,
+        path: &str,
+    ) -> Result<Option<xml::XmlElement>, ApkParseError> {
+        let mut zip_archive = ZipArchive::new(apk)?;
+        let mut file = zip_archive.by_name(path)?;
+        let mut buf = Vec::new();
+        file.read_to_end(&mut buf).map_err(ZipError::from)?;
+        xml::parse_xml(&buf, self.resources.as_ref()).map_err(|source| ApkParseError::XmlError {
+            path: path.to_string(),
+            source,
+        })
+    }
+
+    /// Reorders the methods following the condensed call graph (callers before callees).
+    ///
+    /// Unlike the default DFS order this does not depend on where cycles are entered,
+    /// methods of a recursive group are kept together and sorted by signature.
+    pub fn sort_topologically(&mut self) {
+        let condensation = self.call_graph.condense();
+        let position: HashMap<_, _> = condensation
+            .methods()
+            .enumerate()
+            .map(|(i, sig)| (sig, i))
+            .collect();
+        self.methods
+            .sort_by_key(|m| position.get(&m.signature).copied().unwrap_or(usize::MAX));
+    }
 }
 
 /// A compact version of the `Apk` struct where methods are stored as a vector of opcodes.
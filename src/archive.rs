@@ -0,0 +1,212 @@
+//! Metadata of the entries of the archive
+
+use std::io::{Cursor, Read};
+
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use zip::{extra_fields::ExtraField, read::ZipFile, ZipArchive};
+
+use crate::{dex::is_dex, signing::hex, Apk};
+
+/// Kind of content of an entry, detected from its header
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+pub enum ContentType {
+    Dex,
+    /// Native library or executable
+    Elf,
+    /// Binary XML
+    Axml,
+    Png,
+    Zip,
+    /// Java archive, a ZIP of `.class` files or with a JAR manifest but no Android manifest
+    Jar,
+    Unknown,
+}
+
+impl ContentType {
+    pub(crate) fn detect(name: &str, buf: &[u8]) -> Self {
+        if is_dex(buf) {
+            ContentType::Dex
+        } else if buf.starts_with(b"\x7fELF") {
+            ContentType::Elf
+        } else if buf.starts_with(&[0x03, 0x00, 0x08, 0x00]) {
+            ContentType::Axml
+        } else if buf.starts_with(b"\x89PNG\r\n\x1a\n") {
+            ContentType::Png
+        } else if buf.starts_with(b"PK\x03\x04") || buf.starts_with(b"PK\x05\x06") {
+            Self::detect_zip(name, buf)
+        } else {
+            ContentType::Unknown
+        }
+    }
+
+    fn detect_zip(name: &str, buf: &[u8]) -> Self {
+        if name.ends_with(".jar") {
+            return ContentType::Jar;
+        }
+        let Ok(archive) = ZipArchive::new(Cursor::new(buf)) else {
+            return ContentType::Zip;
+        };
+        let mut names = archive.file_names();
+        if archive.index_for_name("AndroidManifest.xml").is_none()
+            && names.any(|n| n.ends_with(".class") || n == "META-INF/MANIFEST.MF")
+        {
+            ContentType::Jar
+        } else {
+            ContentType::Zip
+        }
+    }
+}
+
+/// An entry of the archive with its metadata
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ArchiveEntry {
+    pub name: String,
+    /// Uncompressed size
+    pub size: u64,
+    pub compressed_size: u64,
+    /// Compression method, e.g. `Stored` or `Deflated`
+    pub compression: String,
+    pub crc32: u32,
+    /// MS-DOS modification time (`YYYY-MM-DD HH:MM:SS`), in the local time of the packager
+    pub last_modified: Option<String>,
+    /// Modification time of the extended timestamp field, seconds since the Unix epoch
+    pub modified_time: Option<u32>,
+    /// Access time of the extended timestamp field, seconds since the Unix epoch
+    pub access_time: Option<u32>,
+    /// Creation time of the extended timestamp field, seconds since the Unix epoch
+    pub creation_time: Option<u32>,
+    /// Whether the encryption flag is set, Android ignores it but most tools refuse to extract
+    /// such entries
+    pub encrypted: bool,
+    /// Lowercase hex SHA-256 of the uncompressed content, `None` if it was not read or hashed
+    pub sha256: Option<String>,
+    /// `Unknown` if the content was not read
+    pub content_type: ContentType,
+}
+
+impl ArchiveEntry {
+    /// Metadata of `file`, `content` being its uncompressed content if it was read, hashed
+    /// if `hash` is set
+    pub(crate) fn new<R: Read>(file: &ZipFile<R>, content: Option<&[u8]>, hash: bool) -> Self {
+        let timestamp = file.extra_data_fields().find_map(|field| match field {
+            ExtraField::ExtendedTimestamp(timestamp) => Some(timestamp),
+            _ => None,
+        });
+        ArchiveEntry {
+            name: file.name().to_string(),
+            size: file.size(),
+            compressed_size: file.compressed_size(),
+            compression: file.compression().to_string(),
+            crc32: file.crc32(),
+            last_modified: file.last_modified().map(|t| t.to_string()),
+            modified_time: timestamp.and_then(|t| t.mod_time()),
+            access_time: timestamp.and_then(|t| t.ac_time()),
+            creation_time: timestamp.and_then(|t| t.cr_time()),
+            encrypted: file.encrypted(),
+            sha256: content.filter(|_| hash).map(|c| hex(&Sha256::digest(c))),
+            content_type: content
+                .map(|c| ContentType::detect(file.name(), c))
+                .unwrap_or(ContentType::Unknown),
+        }
+    }
+}
+
+/// A payload of the archive analysed on its own, the dropped APK of a dropper for example
+#[derive(Debug, Serialize)]
+pub struct Embedded {
+    /// Path of the payload in its parent archive
+    pub path: String,
+    /// `Dex`, `Zip` or `Jar`
+    pub content_type: ContentType,
+    /// Analysis of the payload, a DEX file has no manifest nor archive entries
+    pub apk: Apk,
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        fs::File,
+        io::{Cursor, Read},
+    };
+
+    use zip::{write::SimpleFileOptions, ZipArchive, ZipWriter};
+
+    use super::{ArchiveEntry, ContentType};
+
+    fn zip(names: &[&str]) -> Vec<u8> {
+        let mut writer = ZipWriter::new(Cursor::new(Vec::new()));
+        for name in names {
+            writer
+                .start_file(*name, SimpleFileOptions::default())
+                .unwrap();
+        }
+        writer.finish().unwrap().into_inner()
+    }
+
+    #[test]
+    fn test_detect() {
+        let mut dex = Vec::new();
+        File::open("tests/dex/hello_world.dex")
+            .unwrap()
+            .read_to_end(&mut dex)
+            .unwrap();
+        assert_eq!(ContentType::detect("classes.dex", &dex), ContentType::Dex);
+        assert_eq!(
+            ContentType::detect("assets/payload.bin", &dex),
+            ContentType::Dex
+        );
+        assert_eq!(
+            ContentType::detect("lib/arm64-v8a/libfoo.so", b"\x7fELF\x02\x01"),
+            ContentType::Elf
+        );
+        assert_eq!(
+            ContentType::detect("res/xml/a.xml", &[0x03, 0x00, 0x08, 0x00, 0x10]),
+            ContentType::Axml
+        );
+        assert_eq!(
+            ContentType::detect("res/a.png", b"\x89PNG\r\n\x1a\n\0\0"),
+            ContentType::Png
+        );
+        assert_eq!(
+            ContentType::detect(
+                "assets/a.bin",
+                &zip(&["AndroidManifest.xml", "classes.dex"])
+            ),
+            ContentType::Zip
+        );
+        assert_eq!(
+            ContentType::detect("assets/a.bin", &zip(&["META-INF/MANIFEST.MF", "a/B.class"])),
+            ContentType::Jar
+        );
+        assert_eq!(
+            ContentType::detect("assets/a.jar", &zip(&["classes.dex"])),
+            ContentType::Jar
+        );
+        assert_eq!(
+            ContentType::detect("assets/a.txt", b"dex"),
+            ContentType::Unknown
+        );
+    }
+
+    #[test]
+    fn test_entry() {
+        let mut archive = ZipArchive::new(File::open("tests/apk/signed.apk").unwrap()).unwrap();
+        let mut file = archive.by_name("res/raw/hello.txt").unwrap();
+        let mut buf = Vec::new();
+        file.read_to_end(&mut buf).unwrap();
+        let entry = ArchiveEntry::new(&file, Some(&buf), true);
+        assert_eq!(entry.size, 6);
+        assert_eq!(entry.compressed_size, 6);
+        assert_eq!(entry.compression, "Stored");
+        assert_eq!(entry.crc32, 0x363a3020);
+        assert_eq!(entry.last_modified.as_deref(), Some("2020-01-01 00:00:00"));
+        assert_eq!(entry.modified_time, None);
+        assert!(!entry.encrypted);
+        assert_eq!(
+            entry.sha256.as_deref(),
+            Some("5891b5b522d5df086d0ff0b110fbd9d21bb4fc7163af34d08286a2e846f6be03")
+        );
+        assert_eq!(entry.content_type, ContentType::Unknown);
+    }
+}
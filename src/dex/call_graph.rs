@@ -0,0 +1,325 @@
+use std::{
+    cmp::Reverse,
+    collections::{BTreeSet, BinaryHeap, HashMap, HashSet},
+};
+
+use serde::Serialize;
+
+use super::method::Signature;
+
+/// How an edge of the call graph was discovered
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+pub enum EdgeKind {
+    /// An `invoke-*` instruction in the caller
+    Invoke,
+    /// A callback the framework runs on behalf of the caller, see `CallbackModel`
+    Callback,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize)]
+pub struct Edge {
+    pub target: Signature,
+    pub kind: EdgeKind,
+}
+
+/// Call graph of the methods that have code in the DEX(es).
+///
+/// Every method with code is a node, edges may point to methods outside of the DEX(es)
+/// (e.g. framework methods) which have no outgoing edges of their own.
+#[derive(Debug, Default)]
+pub struct CallGraph {
+    edges: HashMap<Signature, Vec<Edge>>,
+}
+
+impl CallGraph {
+    /// Adds a method together with the methods it invokes
+    pub fn insert(&mut self, caller: Signature, callees: Vec<Signature>) {
+        self.edges.insert(
+            caller,
+            callees
+                .into_iter()
+                .map(|target| Edge {
+                    target,
+                    kind: EdgeKind::Invoke,
+                })
+                .collect(),
+        );
+    }
+
+    /// Adds an edge from a method already present in the graph, duplicates are ignored
+    pub fn add_edge(&mut self, caller: &Signature, target: Signature, kind: EdgeKind) {
+        if let Some(edges) = self.edges.get_mut(caller) {
+            let edge = Edge { target, kind };
+            if !edges.contains(&edge) {
+                edges.push(edge);
+            }
+        }
+    }
+
+    /// Returns `true` if the method has code in the DEX(es)
+    pub fn contains(&self, method: &Signature) -> bool {
+        self.edges.contains_key(method)
+    }
+
+    /// Methods with code in the DEX(es), in no particular order
+    pub fn methods(&self) -> impl Iterator<Item = &Signature> {
+        self.edges.keys()
+    }
+
+    /// Outgoing edges of a method, in the order they were discovered
+    pub fn callees(&self, caller: &Signature) -> &[Edge] {
+        self.edges.get(caller).map_or(&[], Vec::as_slice)
+    }
+
+    pub fn len(&self) -> usize {
+        self.edges.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.edges.is_empty()
+    }
+
+    /// Methods (with or without code) reachable from the given roots, roots included
+    pub fn reachable<'a>(
+        &'a self,
+        roots: impl IntoIterator<Item = &'a Signature>,
+    ) -> HashSet<&'a Signature> {
+        let mut visited = HashSet::new();
+        let mut stack: Vec<_> = roots.into_iter().collect();
+        while let Some(method) = stack.pop() {
+            if visited.insert(method) {
+                stack.extend(self.callees(method).iter().map(|e| &e.target));
+            }
+        }
+        visited
+    }
+
+    /// Strongly connected components of the methods with code, see `Condensation`
+    pub fn strongly_connected_components(&self) -> Vec<Vec<&Signature>> {
+        self.condense().components
+    }
+
+    /// Recursive methods, each group is either a single method calling itself
+    /// or methods calling each other (mutual recursion)
+    pub fn recursive_groups(&self) -> Vec<Vec<&Signature>> {
+        self.condense()
+            .components
+            .into_iter()
+            .filter(|c| c.len() > 1 || self.callees(c[0]).iter().any(|e| &e.target == c[0]))
+            .collect()
+    }
+
+    /// Condenses every strongly connected component into a single node, the result is a DAG.
+    ///
+    /// Components are in a deterministic topological order (callers before callees),
+    /// ties are broken by the smallest signature of each component.
+    pub fn condense(&self) -> Condensation<'_> {
+        let mut nodes: Vec<&Signature> = self.edges.keys().collect();
+        nodes.sort();
+        let index_of: HashMap<&Signature, usize> =
+            nodes.iter().enumerate().map(|(i, &n)| (n, i)).collect();
+        let successors: Vec<Vec<usize>> = nodes
+            .iter()
+            .map(|&n| {
+                self.callees(n)
+                    .iter()
+                    .filter_map(|e| index_of.get(&e.target).copied())
+                    .collect()
+            })
+            .collect();
+
+        // Tarjan's algorithm, iterative to survive deep call chains
+        let count = nodes.len();
+        let mut index = vec![usize::MAX; count];
+        let mut low = vec![0; count];
+        let mut on_stack = vec![false; count];
+        let mut stack = Vec::new();
+        let mut next = 0;
+        let mut sccs: Vec<Vec<usize>> = Vec::new();
+        for root in 0..count {
+            if index[root] != usize::MAX {
+                continue;
+            }
+            let mut work = vec![(root, 0)];
+            index[root] = next;
+            low[root] = next;
+            next += 1;
+            stack.push(root);
+            on_stack[root] = true;
+            while let Some(&(v, i)) = work.last() {
+                if let Some(&w) = successors[v].get(i) {
+                    work.last_mut().unwrap().1 += 1;
+                    if index[w] == usize::MAX {
+                        index[w] = next;
+                        low[w] = next;
+                        next += 1;
+                        stack.push(w);
+                        on_stack[w] = true;
+                        work.push((w, 0));
+                    } else if on_stack[w] {
+                        low[v] = low[v].min(index[w]);
+                    }
+                } else {
+                    work.pop();
+                    if let Some(&(u, _)) = work.last() {
+                        low[u] = low[u].min(low[v]);
+                    }
+                    if low[v] == index[v] {
+                        let mut scc = Vec::new();
+                        while let Some(w) = stack.pop() {
+                            on_stack[w] = false;
+                            scc.push(w);
+                            if w == v {
+                                break;
+                            }
+                        }
+                        // Node indices follow the signature order
+                        scc.sort_unstable();
+                        sccs.push(scc);
+                    }
+                }
+            }
+        }
+
+        let mut scc_of = vec![0; count];
+        for (c, scc) in sccs.iter().enumerate() {
+            for &n in scc {
+                scc_of[n] = c;
+            }
+        }
+        let mut scc_edges = vec![BTreeSet::new(); sccs.len()];
+        let mut in_degree = vec![0; sccs.len()];
+        for (v, succ) in successors.iter().enumerate() {
+            for &w in succ {
+                let (from, to) = (scc_of[v], scc_of[w]);
+                if from != to && scc_edges[from].insert(to) {
+                    in_degree[to] += 1;
+                }
+            }
+        }
+
+        // Kahn's algorithm, picking the component with the smallest signature first
+        let mut ready: BinaryHeap<_> = (0..sccs.len())
+            .filter(|&c| in_degree[c] == 0)
+            .map(|c| Reverse((sccs[c][0], c)))
+            .collect();
+        let mut position = vec![0; sccs.len()];
+        let mut order = Vec::with_capacity(sccs.len());
+        while let Some(Reverse((_, c))) = ready.pop() {
+            position[c] = order.len();
+            order.push(c);
+            for &to in &scc_edges[c] {
+                in_degree[to] -= 1;
+                if in_degree[to] == 0 {
+                    ready.push(Reverse((sccs[to][0], to)));
+                }
+            }
+        }
+
+        let nodes = &nodes;
+        Condensation {
+            component_index: order
+                .iter()
+                .enumerate()
+                .flat_map(|(i, &c)| sccs[c].iter().map(move |&n| (nodes[n], i)))
+                .collect(),
+            components: order
+                .iter()
+                .map(|&c| sccs[c].iter().map(|&n| nodes[n]).collect())
+                .collect(),
+            edges: order
+                .iter()
+                .map(|&c| {
+                    let mut edges: Vec<_> = scc_edges[c].iter().map(|&to| position[to]).collect();
+                    edges.sort_unstable();
+                    edges
+                })
+                .collect(),
+        }
+    }
+}
+
+/// DAG of the strongly connected components of a `CallGraph`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Condensation<'a> {
+    /// Components in topological order (callers before callees), methods of a component are sorted
+    pub components: Vec<Vec<&'a Signature>>,
+    /// Indices of the components called by each component, sorted
+    pub edges: Vec<Vec<usize>>,
+    /// Index of the component of each method, see `Condensation::component_of`
+    component_index: HashMap<&'a Signature, usize>,
+}
+
+impl<'a> Condensation<'a> {
+    /// Index of the component containing a method
+    pub fn component_of(&self, method: &Signature) -> Option<usize> {
+        self.component_index.get(method).copied()
+    }
+
+    /// Methods in topological order, callers first
+    pub fn methods(&self) -> impl Iterator<Item = &'a Signature> + '_ {
+        self.components.iter().flatten().copied()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{CallGraph, Signature};
+
+    fn sig(method_name: &str) -> Signature {
+        Signature {
+            class_type: "LTest;".into(),
+            method_name: method_name.into(),
+            params: None,
+            return_type: "V".into(),
+        }
+    }
+
+    fn graph(edges: &[(&str, &[&str])]) -> CallGraph {
+        let mut call_graph = CallGraph::default();
+        for (caller, callees) in edges {
+            call_graph.insert(sig(caller), callees.iter().map(|c| sig(c)).collect());
+        }
+        call_graph
+    }
+
+    #[test]
+    fn test_condense() {
+        // main -> a <-> b -> c, main -> c, c -> c, c -> println (no code)
+        let call_graph = graph(&[
+            ("main", &["a", "c"]),
+            ("a", &["b"]),
+            ("b", &["a", "c"]),
+            ("c", &["c", "println"]),
+        ]);
+        let condensation = call_graph.condense();
+        let names: Vec<Vec<&str>> = condensation
+            .components
+            .iter()
+            .map(|c| c.iter().map(|s| s.method_name.as_str()).collect())
+            .collect();
+        assert_eq!(names, vec![vec!["main"], vec!["a", "b"], vec!["c"]]);
+        assert_eq!(condensation.edges, vec![vec![1, 2], vec![2], vec![]]);
+        assert_eq!(condensation.component_of(&sig("b")), Some(1));
+        assert_eq!(condensation.component_of(&sig("println")), None);
+
+        let recursive: Vec<Vec<&str>> = call_graph
+            .recursive_groups()
+            .iter()
+            .map(|c| c.iter().map(|s| s.method_name.as_str()).collect())
+            .collect();
+        assert_eq!(recursive, vec![vec!["a", "b"], vec!["c"]]);
+    }
+
+    #[test]
+    fn test_deterministic_order() {
+        // Independent roots are ordered by signature
+        let call_graph = graph(&[("z", &["y"]), ("y", &[]), ("x", &[]), ("w", &["x"])]);
+        let order: Vec<&str> = call_graph
+            .condense()
+            .methods()
+            .map(|s| s.method_name.as_str())
+            .collect();
+        assert_eq!(order, vec!["w", "x", "z", "y"]);
+    }
+}
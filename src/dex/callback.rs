@@ -0,0 +1,375 @@
+use std::collections::{HashMap, HashSet};
+
+use super::{
+    call_graph::{CallGraph, EdgeKind},
+    method::Signature,
+};
+
+/// A framework API that eventually runs a callback of an object handed to it,
+/// e.g. `Thread.start` runs `Runnable.run`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CallbackRule {
+    /// Descriptors of the classes declaring the registration methods, e.g. `Ljava/lang/Thread;`.
+    /// An empty list matches any class, which suits distinctive names like `setOnClickListener`.
+    pub class_types: Vec<String>,
+    /// Names of the registration methods, e.g. `start`
+    pub methods: Vec<String>,
+    /// Names of the callbacks the framework runs, e.g. `run`
+    pub callbacks: Vec<String>,
+}
+
+impl CallbackRule {
+    pub fn new(class_types: &[&str], methods: &[&str], callbacks: &[&str]) -> Self {
+        let owned = |s: &[&str]| s.iter().map(|s| s.to_string()).collect();
+        Self {
+            class_types: owned(class_types),
+            methods: owned(methods),
+            callbacks: owned(callbacks),
+        }
+    }
+
+    /// Whether `call` registers a callback, including on subclasses of the rule's classes
+    /// (e.g. `MyThread.start`)
+    fn matches(&self, call: &Signature, supertypes: &HashMap<String, Vec<String>>) -> bool {
+        self.methods.contains(&call.method_name)
+            && (self.class_types.is_empty()
+                || inherits(supertypes, &call.class_type, &self.class_types))
+    }
+}
+
+/// Whether `class_type` is one of `targets` or extends or implements one of them, following the
+/// superclasses and interfaces of `supertypes`
+fn inherits(
+    supertypes: &HashMap<String, Vec<String>>,
+    class_type: &str,
+    targets: &[String],
+) -> bool {
+    let mut seen = HashSet::new();
+    let mut stack = vec![class_type];
+    while let Some(class) = stack.pop() {
+        if targets.iter().any(|t| t == class) {
+            return true;
+        }
+        if seen.insert(class) {
+            stack.extend(
+                supertypes
+                    .get(class)
+                    .into_iter()
+                    .flatten()
+                    .map(String::as_str),
+            );
+        }
+    }
+    false
+}
+
+/// Model of the implicit control flow through framework callbacks.
+///
+/// The receiving object of a callback is not known without data flow analysis, so candidates are:
+/// * the invoked class, when it is a subclass defined in the DEX(es) (e.g. `MyTask.execute`)
+/// * classes instantiated by the caller (e.g. `new Thread(new Foo$1()).start()`)
+/// * the class of the caller itself (e.g. an activity implementing `OnClickListener`)
+///
+/// For each candidate defining one of the rule's callbacks a `EdgeKind::Callback` edge is added.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CallbackModel {
+    pub rules: Vec<CallbackRule>,
+}
+
+impl CallbackModel {
+    /// A model without any rules, no synthetic edges are added
+    pub fn empty() -> Self {
+        Self { rules: Vec::new() }
+    }
+
+    pub fn with_rule(mut self, rule: CallbackRule) -> Self {
+        self.rules.push(rule);
+        self
+    }
+
+    /// Adds the synthetic edges to the call graph.
+    ///
+    /// ### Arguments
+    /// * `call_graph`: Graph built from the `invoke-*` instructions.
+    /// * `instantiated`: Class descriptors of the `new-instance` instructions of each method.
+    /// * `supertypes`: Superclass and interfaces of each class of the DEX(es).
+    pub(crate) fn link(
+        &self,
+        call_graph: &mut CallGraph,
+        instantiated: &HashMap<Signature, HashSet<String>>,
+        supertypes: &HashMap<String, Vec<String>>,
+    ) {
+        if self.rules.is_empty() {
+            return;
+        }
+
+        let mut by_class: HashMap<&str, Vec<&Signature>> = HashMap::new();
+        for method in call_graph.methods() {
+            by_class
+                .entry(method.class_type.as_str())
+                .or_default()
+                .push(method);
+        }
+
+        let mut edges = Vec::new();
+        for caller in call_graph.methods() {
+            for edge in call_graph.callees(caller) {
+                let call = &edge.target;
+                if call_graph.contains(call) {
+                    // Overridden in the DEX(es), the invoke edge already leads to the code
+                    continue;
+                }
+                let app_class = by_class.contains_key(call.class_type.as_str());
+                for rule in self.rules.iter().filter(|r| r.matches(call, supertypes)) {
+                    let candidates = app_class
+                        .then_some(call.class_type.as_str())
+                        .into_iter()
+                        .chain(
+                            instantiated
+                                .get(caller)
+                                .into_iter()
+                                .flatten()
+                                .map(String::as_str),
+                        )
+                        .chain(std::iter::once(caller.class_type.as_str()));
+                    for class in candidates {
+                        for &callback in by_class.get(class).into_iter().flatten() {
+                            if rule.callbacks.contains(&callback.method_name) {
+                                edges.push((caller.clone(), callback.clone()));
+                            }
+                        }
+                    }
+                }
+            }
+        }
+
+        for (caller, callback) in edges {
+            call_graph.add_edge(&caller, callback, EdgeKind::Callback);
+        }
+    }
+}
+
+/// Threads, executors, handlers, timers and the common `View` listeners
+impl Default for CallbackModel {
+    fn default() -> Self {
+        Self::empty()
+            .with_rule(CallbackRule::new(
+                &["Ljava/lang/Thread;"],
+                &["start"],
+                &["run"],
+            ))
+            .with_rule(CallbackRule::new(
+                &["Landroid/os/AsyncTask;"],
+                &["execute", "executeOnExecutor"],
+                &[
+                    "onPreExecute",
+                    "doInBackground",
+                    "onProgressUpdate",
+                    "onPostExecute",
+                    "onCancelled",
+                ],
+            ))
+            .with_rule(CallbackRule::new(
+                &["Landroid/os/Handler;", "Landroid/view/View;"],
+                &["post", "postDelayed", "postAtTime", "postAtFrontOfQueue"],
+                &["run"],
+            ))
+            .with_rule(CallbackRule::new(
+                &["Landroid/os/Handler;"],
+                &[
+                    "sendMessage",
+                    "sendMessageDelayed",
+                    "sendMessageAtTime",
+                    "sendEmptyMessage",
+                    "sendEmptyMessageDelayed",
+                ],
+                &["handleMessage"],
+            ))
+            .with_rule(CallbackRule::new(
+                &["Landroid/app/Activity;"],
+                &["runOnUiThread"],
+                &["run"],
+            ))
+            .with_rule(CallbackRule::new(
+                &[
+                    "Ljava/util/concurrent/Executor;",
+                    "Ljava/util/concurrent/ExecutorService;",
+                    "Ljava/util/concurrent/ScheduledExecutorService;",
+                    "Ljava/util/concurrent/ThreadPoolExecutor;",
+                    "Ljava/util/concurrent/ScheduledThreadPoolExecutor;",
+                ],
+                &[
+                    "execute",
+                    "submit",
+                    "schedule",
+                    "scheduleAtFixedRate",
+                    "scheduleWithFixedDelay",
+                ],
+                &["run", "call"],
+            ))
+            .with_rule(CallbackRule::new(
+                &["Ljava/util/Timer;"],
+                &["schedule", "scheduleAtFixedRate"],
+                &["run"],
+            ))
+            .with_rule(CallbackRule::new(
+                &[],
+                &["setOnClickListener"],
+                &["onClick"],
+            ))
+            .with_rule(CallbackRule::new(
+                &[],
+                &["setOnLongClickListener"],
+                &["onLongClick"],
+            ))
+            .with_rule(CallbackRule::new(
+                &[],
+                &["setOnTouchListener"],
+                &["onTouch"],
+            ))
+            .with_rule(CallbackRule::new(
+                &[],
+                &["setOnItemClickListener"],
+                &["onItemClick"],
+            ))
+            .with_rule(CallbackRule::new(
+                &[],
+                &["setOnCheckedChangeListener"],
+                &["onCheckedChanged"],
+            ))
+            .with_rule(CallbackRule::new(
+                &[],
+                &["addTextChangedListener"],
+                &["beforeTextChanged", "onTextChanged", "afterTextChanged"],
+            ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::{HashMap, HashSet};
+
+    use crate::dex::{
+        call_graph::{CallGraph, Edge, EdgeKind},
+        method::Signature,
+    };
+
+    use super::{CallbackModel, CallbackRule};
+
+    fn sig(class_type: &str, method_name: &str) -> Signature {
+        Signature {
+            class_type: class_type.into(),
+            method_name: method_name.into(),
+            params: None,
+            return_type: "V".into(),
+        }
+    }
+
+    #[test]
+    fn test_instantiated_runnable() {
+        let caller = sig("LMain;", "onCreate");
+        let run = sig("LMain$1;", "run");
+        let mut call_graph = CallGraph::default();
+        call_graph.insert(caller.clone(), vec![sig("Ljava/lang/Thread;", "start")]);
+        call_graph.insert(run.clone(), vec![]);
+        let instantiated =
+            HashMap::from([(caller.clone(), HashSet::from(["LMain$1;".to_string()]))]);
+
+        CallbackModel::default().link(&mut call_graph, &instantiated, &HashMap::new());
+
+        assert_eq!(
+            call_graph.callees(&caller),
+            &[
+                Edge {
+                    target: sig("Ljava/lang/Thread;", "start"),
+                    kind: EdgeKind::Invoke
+                },
+                Edge {
+                    target: run,
+                    kind: EdgeKind::Callback
+                }
+            ]
+        );
+    }
+
+    #[test]
+    fn test_subclass_and_listener() {
+        let caller = sig("LMain;", "onCreate");
+        let run = sig("LWorker;", "run");
+        let on_click = sig("LMain;", "onClick");
+        let mut call_graph = CallGraph::default();
+        call_graph.insert(
+            caller.clone(),
+            vec![
+                sig("LWorker;", "start"),
+                sig("Landroid/widget/Button;", "setOnClickListener"),
+            ],
+        );
+        call_graph.insert(run.clone(), vec![]);
+        call_graph.insert(on_click.clone(), vec![]);
+        let supertypes = HashMap::from([
+            ("LWorker;".to_string(), vec!["LBaseWorker;".to_string()]),
+            (
+                "LBaseWorker;".to_string(),
+                vec!["Ljava/lang/Thread;".to_string()],
+            ),
+        ]);
+
+        CallbackModel::default().link(&mut call_graph, &HashMap::new(), &supertypes);
+
+        let callbacks: Vec<_> = call_graph
+            .callees(&caller)
+            .iter()
+            .filter(|e| e.kind == EdgeKind::Callback)
+            .map(|e| &e.target)
+            .collect();
+        assert_eq!(callbacks, vec![&run, &on_click]);
+    }
+
+    #[test]
+    fn test_unrelated_class() {
+        // `MyPlayer.start` shares its name with `Thread.start`
+        let caller = sig("LMain;", "onCreate");
+        let run = sig("LMyPlayer;", "run");
+        let mut call_graph = CallGraph::default();
+        call_graph.insert(caller.clone(), vec![sig("LMyPlayer;", "start")]);
+        call_graph.insert(run, vec![]);
+        let supertypes = HashMap::from([(
+            "LMyPlayer;".to_string(),
+            vec![
+                "Ljava/lang/Object;".to_string(),
+                "Ljava/lang/AutoCloseable;".to_string(),
+            ],
+        )]);
+
+        CallbackModel::default().link(&mut call_graph, &HashMap::new(), &supertypes);
+        assert_eq!(call_graph.callees(&caller).len(), 1);
+    }
+
+    #[test]
+    fn test_custom_rule() {
+        let caller = sig("LMain;", "go");
+        let callback = sig("LMain;", "onResult");
+        let mut call_graph = CallGraph::default();
+        call_graph.insert(caller.clone(), vec![sig("Lcom/sdk/Api;", "request")]);
+        call_graph.insert(callback.clone(), vec![]);
+
+        CallbackModel::empty().link(&mut call_graph, &HashMap::new(), &HashMap::new());
+        assert_eq!(call_graph.callees(&caller).len(), 1);
+
+        CallbackModel::empty()
+            .with_rule(CallbackRule::new(
+                &["Lcom/sdk/Api;"],
+                &["request"],
+                &["onResult"],
+            ))
+            .link(&mut call_graph, &HashMap::new(), &HashMap::new());
+        assert_eq!(
+            call_graph.callees(&caller)[1],
+            Edge {
+                target: callback,
+                kind: EdgeKind::Callback
+            }
+        );
+    }
+}
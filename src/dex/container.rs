@@ -0,0 +1,188 @@
+//! DEX headers, including the container format of version 041 where one file holds several
+//! DEX files (e.g. a whole multidex app in a single `classes.dex`)
+
+use dex::{Dex, DexReader};
+
+/// Size of the header before version 041
+const HEADER_SIZE: usize = 0x70;
+/// Size of the header of a 041 section, with `container_size` and `header_offset`
+const CONTAINER_HEADER_SIZE: usize = 0x78;
+const FILE_SIZE: usize = 0x20;
+const HEADER_OFFSET: usize = 0x74;
+/// `string_ids_off`, `type_ids_off`, `proto_ids_off`, `field_ids_off`, `method_ids_off` and
+/// `class_defs_off`, the offsets relative to the header of a section
+const ID_OFFSETS: [usize; 6] = [0x3C, 0x44, 0x4C, 0x54, 0x5C, 0x64];
+/// Most sections read from a 041 container, each one being a copy of the whole container
+const MAX_SECTIONS: usize = 64;
+
+/// Version of the DEX file starting at the beginning of `buf`, `None` if it has no DEX header
+fn version(buf: &[u8]) -> Option<u32> {
+    match buf.get(..8)? {
+        [b'd', b'e', b'x', b'\n', digits @ .., 0] if digits.iter().all(u8::is_ascii_digit) => {
+            std::str::from_utf8(digits).ok()?.parse().ok()
+        }
+        _ => None,
+    }
+}
+
+/// Whether `buf` starts with the header of a supported DEX version (035 to 041)
+pub(crate) fn is_dex(buf: &[u8]) -> bool {
+    version(buf).is_some_and(|v| (35..=41).contains(&v))
+}
+
+fn read_u32(buf: &[u8], offset: usize) -> Option<usize> {
+    let bytes = buf.get(offset..offset + 4)?;
+    Some(u32::from_le_bytes(bytes.try_into().ok()?) as usize)
+}
+
+fn write_u32(buf: &mut [u8], offset: usize, value: usize) {
+    buf[offset..offset + 4].copy_from_slice(&(value as u32).to_le_bytes());
+}
+
+/// Reads the DEX files of `buf`, one for most versions and one per section of a 041 container.
+///
+/// `DexReader` only knows the versions up to 039, 040 shares their layout and is read as such.
+pub(crate) fn read_dex(mut buf: Vec<u8>) -> Result<Vec<Dex<Vec<u8>>>, dex::Error> {
+    if version(&buf) == Some(41) {
+        return sections(&buf)?
+            .into_iter()
+            .map(DexReader::from_vec)
+            .collect();
+    }
+    if version(&buf) == Some(40) {
+        buf[4..8].copy_from_slice(b"039\0");
+    }
+    Ok(vec![DexReader::from_vec(buf)?])
+}
+
+/// Sections of a 041 container, each as a standalone DEX file.
+///
+/// The id tables of a section are relative to its header while the data (strings, code, ...)
+/// is relative to the container, so a section is the whole container with the header of the
+/// section moved to the start and its id offsets rebased.
+///
+/// Fails if a section header is malformed or if there are more than `MAX_SECTIONS` sections.
+fn sections(buf: &[u8]) -> Result<Vec<Vec<u8>>, dex::Error> {
+    let mut sections = Vec::new();
+    let mut offset = 0;
+    while offset < buf.len() {
+        if sections.len() == MAX_SECTIONS {
+            return Err(dex::Error::MalFormed(format!(
+                "DEX container with more than {MAX_SECTIONS} sections"
+            )));
+        }
+        let header = &buf[offset..];
+        let size = match (version(header), read_u32(header, FILE_SIZE)) {
+            (Some(41), Some(size))
+                if read_u32(header, HEADER_OFFSET) == Some(offset)
+                    && (CONTAINER_HEADER_SIZE..=header.len()).contains(&size) =>
+            {
+                size
+            }
+            _ => {
+                return Err(dex::Error::MalFormed(format!(
+                    "DEX container without a section header at {offset:#x}"
+                )))
+            }
+        };
+
+        let mut section = buf.to_vec();
+        section.copy_within(offset..offset + HEADER_SIZE, 0);
+        section[4..8].copy_from_slice(b"039\0");
+        write_u32(&mut section, FILE_SIZE, buf.len());
+        for field in ID_OFFSETS {
+            match read_u32(&section, field) {
+                Some(0) | None => {}
+                Some(value) => write_u32(&mut section, field, value + offset),
+            }
+        }
+        sections.push(section);
+        offset += size;
+    }
+    Ok(sections)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::{is_dex, read_dex, sections, write_u32, FILE_SIZE, HEADER_OFFSET, MAX_SECTIONS};
+    use crate::{dex::get_methods, options::ParseOptions};
+
+    #[test]
+    fn test_is_dex() {
+        assert!(is_dex(b"dex\n035\0"));
+        assert!(is_dex(b"dex\n039\0"));
+        assert!(is_dex(b"dex\n040\0"));
+        assert!(is_dex(b"dex\n041\0"));
+        assert!(!is_dex(b"dex\n034\0"));
+        assert!(!is_dex(b"dex\n042\0"));
+        assert!(!is_dex(b"dex\n035"));
+        assert!(!is_dex(b"\0dex\n035\0"));
+        assert!(!is_dex(b"PK\x03\x04 dex\n035\0"));
+    }
+
+    #[test]
+    fn test_version_040() {
+        let mut buf = fs::read("tests/dex/hello_world.dex").unwrap();
+        buf[4..8].copy_from_slice(b"040\0");
+        let dexes = read_dex(buf).unwrap();
+        let (methods, ..) = get_methods(
+            dexes.iter().map(|dex| (None, dex)),
+            None,
+            &ParseOptions::default(),
+        )
+        .unwrap();
+        assert_eq!(methods.len(), 2);
+    }
+
+    #[test]
+    fn test_container() {
+        let buf = fs::read("tests/dex/container.dex").unwrap();
+        let dexes = read_dex(buf).unwrap();
+        assert_eq!(dexes.len(), 2);
+
+        let (methods, ..) = get_methods(
+            dexes.iter().map(|dex| (None, dex)),
+            None,
+            &ParseOptions::default(),
+        )
+        .unwrap();
+        let mut classes: Vec<_> = methods
+            .iter()
+            .map(|m| m.signature.class_type.as_str())
+            .collect();
+        classes.sort();
+        classes.dedup();
+        assert_eq!(classes, ["LCallGraph;", "LTestBasic;"]);
+        assert_eq!(methods.len(), 8);
+    }
+
+    #[test]
+    fn test_malformed_container() {
+        let buf = fs::read("tests/dex/container.dex").unwrap();
+        let first = u32::from_le_bytes(buf[FILE_SIZE..FILE_SIZE + 4].try_into().unwrap());
+        let mut broken = buf.clone();
+        write_u32(&mut broken, first as usize + HEADER_OFFSET, 0);
+        assert!(read_dex(broken).is_err());
+        assert!(read_dex(buf[..first as usize + 8].to_vec()).is_err());
+
+        // Empty sections, with only a header
+        let container = |count: usize| {
+            let mut buf = Vec::new();
+            for i in 0..count {
+                let mut header = vec![0; 0x78];
+                header[..8].copy_from_slice(b"dex\n041\0");
+                write_u32(&mut header, FILE_SIZE, 0x78);
+                write_u32(&mut header, HEADER_OFFSET, i * 0x78);
+                buf.extend(header);
+            }
+            buf
+        };
+        assert_eq!(
+            sections(&container(MAX_SECTIONS)).unwrap().len(),
+            MAX_SECTIONS
+        );
+        assert!(sections(&container(MAX_SECTIONS + 1)).is_err());
+    }
+}
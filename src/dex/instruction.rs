@@ -118,6 +118,30 @@ impl Instruction {
             length,
         )))
     }
+
+    /// Returns the type or string referenced by `new-instance`, `const-class` and `const-string*`.
+    ///
+    /// Must be called with the same `code` and `offset` the instruction was parsed from.
+    pub fn reference(&self, code: &[u16], offset: usize) -> Option<Reference> {
+        let index = *code.get(offset + 1)?;
+        match self.opcode {
+            Opcode::NewInstance => Some(Reference::NewInstance(index)),
+            Opcode::ConstClass => Some(Reference::ConstClass(index)),
+            Opcode::ConstString => Some(Reference::ConstString(index as u32)),
+            Opcode::ConstStringJumbo => code
+                .get(offset + 2)
+                .map(|&high| Reference::ConstString(index as u32 | (high as u32) << 16)),
+            _ => None,
+        }
+    }
+}
+
+/// Type or string index referenced by an instruction
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Reference {
+    NewInstance(u16),
+    ConstClass(u16),
+    ConstString(u32),
 }
 
 #[cfg(test)]
@@ -192,4 +216,16 @@ mod tests {
             }
         );
     }
+
+    // Test the string index of a `const-string/jumbo`
+    #[test]
+    fn test_reference_jumbo() {
+        let code: [u16; 3] = [0x1B, 0x0002, 0x0001];
+        let (inst, length) = Instruction::try_from_code(&code, 0).unwrap().unwrap();
+        assert_eq!(length, 3);
+        assert_eq!(
+            inst.reference(&code, 0),
+            Some(Reference::ConstString(0x0001_0002))
+        );
+    }
 }
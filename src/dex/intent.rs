@@ -0,0 +1,220 @@
+use serde::Serialize;
+
+use super::method::Signature;
+
+/// Kind of component an intent is delivered to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize)]
+pub enum IccKind {
+    Activity,
+    Service,
+    Broadcast,
+}
+
+/// Where an intent is addressed to, recovered from constants in the sending method
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize)]
+pub enum IntentTarget {
+    /// Explicit intent, holds the class descriptor of the target (e.g. `Lcom/example/Foo;`)
+    Class(String),
+    /// Implicit intent, holds the action (e.g. `android.intent.action.VIEW`)
+    Action(String),
+}
+
+/// An intent sent by a method (`startActivity`, `startService`, `sendBroadcast`, ...)
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize)]
+pub struct IntentSend {
+    pub method: Signature,
+    pub kind: IccKind,
+    pub target: IntentTarget,
+}
+
+const INTENT: &str = "Landroid/content/Intent;";
+const COMPONENT_NAME: &str = "Landroid/content/ComponentName;";
+const PENDING_INTENT: &str = "Landroid/app/PendingIntent;";
+const CLASS: &str = "Ljava/lang/Class;";
+const STRING: &str = "Ljava/lang/String;";
+
+/// Recovers the intents sent by a single method.
+///
+/// There is no data flow analysis, every target built in the method (`new Intent(ctx, Foo.class)`,
+/// `setClassName`, `setAction`, ...) is paired with every send in the same method. The target
+/// is taken from the last `const-class` or `const-string` before the call consuming it.
+#[derive(Debug, Default)]
+pub(crate) struct IntentScanner {
+    last_class: Option<String>,
+    last_string: Option<String>,
+    targets: Vec<IntentTarget>,
+    sends: Vec<IccKind>,
+}
+
+impl IntentScanner {
+    pub fn const_class(&mut self, class_type: String) {
+        self.last_class = Some(class_type);
+    }
+
+    pub fn const_string(&mut self, string: String) {
+        self.last_string = Some(string);
+    }
+
+    pub fn invoke(&mut self, call: &Signature) {
+        let params = call.params.as_deref().unwrap_or_default();
+        let takes = |t: &str| params.iter().any(|p| p == t);
+        match (call.class_type.as_str(), call.method_name.as_str()) {
+            (INTENT, "<init>" | "setClass") | (COMPONENT_NAME, "<init>") if takes(CLASS) => {
+                if let Some(class) = self.last_class.take() {
+                    self.push(IntentTarget::Class(class));
+                }
+            }
+            (INTENT, "setClassName") | (COMPONENT_NAME, "<init>") if takes(STRING) => {
+                if let Some(name) = self.last_string.take() {
+                    self.push(IntentTarget::Class(format!("L{};", name.replace('.', "/"))));
+                }
+            }
+            (INTENT, "<init>" | "setAction") if params.first().is_some_and(|p| p == STRING) => {
+                if let Some(action) = self.last_string.take() {
+                    self.push(IntentTarget::Action(action));
+                }
+            }
+            (PENDING_INTENT, "getActivity" | "getActivities") => self.sends.push(IccKind::Activity),
+            (PENDING_INTENT, "getService" | "getForegroundService") => {
+                self.sends.push(IccKind::Service)
+            }
+            (PENDING_INTENT, "getBroadcast") => self.sends.push(IccKind::Broadcast),
+            (_, name) => {
+                if let Some(kind) = send_kind(name) {
+                    self.sends.push(kind);
+                }
+            }
+        }
+    }
+
+    fn push(&mut self, target: IntentTarget) {
+        if !self.targets.contains(&target) {
+            self.targets.push(target);
+        }
+    }
+
+    pub fn finish(mut self, method: &Signature) -> Vec<IntentSend> {
+        self.sends.sort_unstable();
+        self.sends.dedup();
+        self.sends
+            .iter()
+            .flat_map(|&kind| {
+                self.targets.iter().map(move |target| IntentSend {
+                    method: method.clone(),
+                    kind,
+                    target: target.clone(),
+                })
+            })
+            .collect()
+    }
+}
+
+/// Context and fragment methods delivering an intent
+fn send_kind(method_name: &str) -> Option<IccKind> {
+    match method_name {
+        "startActivity"
+        | "startActivityForResult"
+        | "startActivities"
+        | "startActivityIfNeeded" => Some(IccKind::Activity),
+        "startService" | "startForegroundService" | "bindService" => Some(IccKind::Service),
+        "sendBroadcast"
+        | "sendOrderedBroadcast"
+        | "sendStickyBroadcast"
+        | "sendBroadcastAsUser"
+        | "sendOrderedBroadcastAsUser" => Some(IccKind::Broadcast),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::dex::method::Signature;
+
+    use super::{IccKind, IntentScanner, IntentSend, IntentTarget};
+
+    fn sig(class_type: &str, method_name: &str, params: &[&str]) -> Signature {
+        Signature {
+            class_type: class_type.into(),
+            method_name: method_name.into(),
+            params: (!params.is_empty()).then(|| params.iter().map(|p| p.to_string()).collect()),
+            return_type: "V".into(),
+        }
+    }
+
+    #[test]
+    fn test_explicit_activity() {
+        let method = sig("Lcom/example/Main;", "onClick", &[]);
+        let mut scanner = IntentScanner::default();
+        scanner.const_class("Lcom/example/Second;".into());
+        scanner.invoke(&sig(
+            "Landroid/content/Intent;",
+            "<init>",
+            &["Landroid/content/Context;", "Ljava/lang/Class;"],
+        ));
+        scanner.invoke(&sig(
+            "Lcom/example/Main;",
+            "startActivity",
+            &["Landroid/content/Intent;"],
+        ));
+        assert_eq!(
+            scanner.finish(&method),
+            vec![IntentSend {
+                method: method.clone(),
+                kind: IccKind::Activity,
+                target: IntentTarget::Class("Lcom/example/Second;".into()),
+            }]
+        );
+    }
+
+    #[test]
+    fn test_action_and_class_name() {
+        let method = sig("Lcom/example/Main;", "notify", &[]);
+        let mut scanner = IntentScanner::default();
+        scanner.const_string("com.example.PING".into());
+        scanner.invoke(&sig(
+            "Landroid/content/Intent;",
+            "<init>",
+            &["Ljava/lang/String;"],
+        ));
+        scanner.const_string("com.example".into());
+        scanner.const_string("com.example.Sync".into());
+        scanner.invoke(&sig(
+            "Landroid/content/Intent;",
+            "setClassName",
+            &["Ljava/lang/String;", "Ljava/lang/String;"],
+        ));
+        scanner.invoke(&sig("Landroid/content/Context;", "sendBroadcast", &[]));
+        let targets: Vec<_> = scanner
+            .finish(&method)
+            .into_iter()
+            .map(|send| (send.kind, send.target))
+            .collect();
+        assert_eq!(
+            targets,
+            vec![
+                (
+                    IccKind::Broadcast,
+                    IntentTarget::Action("com.example.PING".into())
+                ),
+                (
+                    IccKind::Broadcast,
+                    IntentTarget::Class("Lcom/example/Sync;".into())
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_no_send() {
+        let mut scanner = IntentScanner::default();
+        scanner.const_class("Lcom/example/Second;".into());
+        scanner.invoke(&sig(
+            "Landroid/content/Intent;",
+            "<init>",
+            &["Landroid/content/Context;", "Ljava/lang/Class;"],
+        ));
+        assert!(scanner
+            .finish(&sig("Lcom/example/Main;", "build", &[]))
+            .is_empty());
+    }
+}
@@ -37,6 +37,9 @@ pub struct Method {
     pub signature: Signature,
     #[serde(rename = "ins")]
     pub insns: Vec<Instruction>,
+    /// Split APK the method is defined in, `None` for the base APK
+    #[serde(rename = "spl", skip_serializing_if = "Option::is_none")]
+    pub split: Option<String>,
 }
 
 #[derive(Debug, Serialize)]
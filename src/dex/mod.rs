@@ -1,30 +1,56 @@
+mod call_graph;
+mod callback;
+mod container;
 mod errors;
 mod instruction;
+mod intent;
 mod method;
 mod opcode;
 
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 
-use self::method::Signature;
+use self::{instruction::Reference, intent::IntentScanner};
+use crate::options::ParseOptions;
 use dex::Dex;
-use regex::Regex;
 
+pub(crate) use self::container::{is_dex, read_dex};
 pub use self::{
+    call_graph::{CallGraph, Condensation, Edge, EdgeKind},
+    callback::{CallbackModel, CallbackRule},
     errors::DexError,
     instruction::Instruction,
-    method::{CompactMethod, Method},
+    intent::{IccKind, IntentSend, IntentTarget},
+    method::{CompactMethod, Method, Signature},
     opcode::Opcode,
 };
 
-pub fn get_methods(
-    dexes: &[Dex<impl AsRef<[u8]>>],
-    regexes: Option<Vec<Regex>>,
-) -> Result<Vec<Method>, DexError> {
+/// Extracts the methods of the DEX files, each coming with the name of the split APK it belongs
+/// to (`None` for the base APK)
+pub fn get_methods<'a, T: AsRef<[u8]> + 'a>(
+    dexes: impl IntoIterator<Item = (Option<&'a str>, &'a Dex<T>)>,
+    entry_points: Option<&HashSet<String>>,
+    options: &ParseOptions,
+) -> Result<(Vec<Method>, CallGraph, Vec<IntentSend>), DexError> {
     // Extract methods
-    let mut call_graph = HashMap::new();
+    let mut call_graph = CallGraph::default();
     let mut name_map = HashMap::new();
-    for dex in dexes.into_iter() {
-        for class in dex.classes().filter_map(Result::ok) {
+    let mut instantiated = HashMap::new();
+    let mut supertypes = HashMap::new();
+    let mut sends = Vec::new();
+    for (split, dex) in dexes {
+        for class in dex
+            .classes()
+            .filter_map(Result::ok)
+            .filter(|c| options.keeps_class(&c.jtype().to_string()))
+        {
+            let mut parents: Vec<_> = class.interfaces().iter().map(|t| t.to_string()).collect();
+            if let Some(super_class) = class.super_class() {
+                match dex.get_type(super_class) {
+                    Ok(t) => parents.push(t.to_string()),
+                    Err(e) => log::error!("{e}"),
+                }
+            }
+            supertypes.insert(class.jtype().to_string(), parents);
             for method in class.methods() {
                 if let Some(code) = method.code() {
                     let mut offset = 0;
@@ -42,71 +68,86 @@ pub fn get_methods(
                         )
                     };
                     let mut calls = Vec::new();
-                    while let Some((inst, len)) = Instruction::try_from_code(bytecode, offset)
-                        .map_err(|source| DexError {
-                            class_name: class.jtype().to_java_type(),
-                            method_name: method.name().to_string(),
-                            source,
-                        })?
-                    {
+                    let mut new_instances = HashSet::new();
+                    let mut intents = IntentScanner::default();
+                    while options.max_instructions.is_none_or(|max| insns.len() < max) {
+                        let (mut inst, len) = match Instruction::try_from_code(bytecode, offset) {
+                            Ok(Some(decoded)) => decoded,
+                            Ok(None) => break,
+                            Err(source) => {
+                                let e = DexError {
+                                    class_name: class.jtype().to_java_type(),
+                                    method_name: method.name().to_string(),
+                                    source,
+                                };
+                                if options.fatal_errors {
+                                    return Err(e);
+                                }
+                                // Keep the instructions decoded so far
+                                log::error!("{e}");
+                                break;
+                            }
+                        };
                         if let Some(m_idx) = inst.method_id {
-                            match dex.get_method_item(m_idx as u64) {
-                                Ok(method_item) => {
-                                    match (
-                                        dex.get_type(method_item.class_idx() as u32),
-                                        dex.get_string(method_item.name_idx() as u32),
-                                        dex.get_proto_item(method_item.proto_idx() as u64),
-                                    ) {
-                                        (Ok(t), Ok(n), Ok(p)) => {
-                                            match dex.get_type(p.return_type()) {
-                                                Ok(r) => {
-                                                    if p.params_off() == 0 {
-                                                        calls
-                                                            .push(Signature::new(&t, &n, None, &r));
-                                                    } else {
-                                                        match dex.get_interfaces(p.params_off()) {
-                                                            Ok(params) => {
-                                                                calls.push(Signature::new(
-                                                                    &t,
-                                                                    &n,
-                                                                    Some(&params),
-                                                                    &r,
-                                                                ))
-                                                            }
-                                                            Err(e) => log::error!("{e}"),
-                                                        }
-                                                    }
-                                                }
-                                                Err(e) => log::error!("{e}"),
-                                            }
-                                        }
-                                        (Err(e), _, _) | (_, Err(e), _) | (_, _, Err(e)) => {
-                                            log::error!("{e}")
-                                        }
-                                    }
+                            if let Some(call) = get_signature(dex, m_idx) {
+                                intents.invoke(&call);
+                                calls.push(call);
+                            }
+                        }
+                        match inst.reference(bytecode, offset) {
+                            Some(Reference::NewInstance(t_idx)) => match dex.get_type(t_idx as u32)
+                            {
+                                Ok(t) => {
+                                    new_instances.insert(t.to_string());
                                 }
                                 Err(e) => log::error!("{e}"),
+                            },
+                            Some(Reference::ConstClass(t_idx)) => {
+                                match dex.get_type(t_idx as u32) {
+                                    Ok(t) => intents.const_class(t.to_string()),
+                                    Err(e) => log::error!("{e}"),
+                                }
                             }
+                            Some(Reference::ConstString(s_idx)) => match dex.get_string(s_idx) {
+                                Ok(s) => intents.const_string(s.to_string()),
+                                Err(e) => log::error!("{e}"),
+                            },
+                            None => {}
+                        }
+                        if options.opcodes_only {
+                            inst.method_id = None;
                         }
                         insns.push(inst);
                         offset += len;
                     }
-                    let method = Method { signature, insns };
+                    let method = Method {
+                        signature,
+                        insns,
+                        split: split.map(str::to_string),
+                    };
                     call_graph.insert(method.signature.clone(), calls);
+                    if !new_instances.is_empty() {
+                        instantiated.insert(method.signature.clone(), new_instances);
+                    }
+                    sends.extend(intents.finish(&method.signature));
                     name_map.insert(method.signature.clone(), method);
                 }
             }
         }
     }
 
+    options
+        .callbacks
+        .link(&mut call_graph, &instantiated, &supertypes);
+
     // Sort so the manifest components will be prioritized
     let mut flattened = Vec::with_capacity(call_graph.len());
-    let mut stack: Vec<_> = call_graph.keys().collect();
-    if let Some(regexes) = regexes {
+    let mut stack: Vec<_> = call_graph.methods().collect();
+    if let Some(entry_points) = entry_points {
         log::debug!("Sorting by manifest components");
         stack.sort_by_cached_key(|&sig| {
             (
-                regexes.iter().any(|r| r.is_match(&sig.class_type)),
+                is_entry_point(entry_points, &sig.class_type),
                 std::cmp::Reverse(sig),
             )
         });
@@ -119,13 +160,73 @@ pub fn get_methods(
     while let Some(method_name) = stack.pop() {
         if let Some(method) = name_map.remove(method_name) {
             flattened.push(method);
-            if let Some(adjacent_methods) = call_graph.get(method_name) {
-                stack.extend(adjacent_methods.iter().rev());
-            }
+            stack.extend(
+                call_graph
+                    .callees(method_name)
+                    .iter()
+                    .rev()
+                    .map(|e| &e.target),
+            );
         }
     }
 
-    Ok(flattened)
+    Ok((flattened, call_graph, sends))
+}
+
+/// Whether a class is one of the entry point descriptors or nested in one
+/// (e.g. `Lcom/example/Main$1;` in `Lcom/example/Main;`)
+fn is_entry_point(entry_points: &HashSet<String>, class_type: &str) -> bool {
+    enclosing_classes(class_type).any(|c| entry_points.contains(&c))
+}
+
+/// Descriptors of a class and of the classes it is nested in, innermost first
+/// (e.g. `Lcom/example/Outer$Inner$1;`, `Lcom/example/Outer$Inner;` then `Lcom/example/Outer;`)
+pub(crate) fn enclosing_classes(class_type: &str) -> impl Iterator<Item = String> + '_ {
+    std::iter::once(class_type.to_string()).chain(
+        class_type
+            .rmatch_indices('$')
+            .map(|(i, _)| format!("{};", &class_type[..i])),
+    )
+}
+
+/// Resolves the signature of an invoked method, logging any malformed index
+fn get_signature(dex: &Dex<impl AsRef<[u8]>>, m_idx: u16) -> Option<Signature> {
+    let method_item = match dex.get_method_item(m_idx as u64) {
+        Ok(method_item) => method_item,
+        Err(e) => {
+            log::error!("{e}");
+            return None;
+        }
+    };
+    let (t, n, p) = match (
+        dex.get_type(method_item.class_idx() as u32),
+        dex.get_string(method_item.name_idx() as u32),
+        dex.get_proto_item(method_item.proto_idx() as u64),
+    ) {
+        (Ok(t), Ok(n), Ok(p)) => (t, n, p),
+        (Err(e), _, _) | (_, Err(e), _) | (_, _, Err(e)) => {
+            log::error!("{e}");
+            return None;
+        }
+    };
+    let r = match dex.get_type(p.return_type()) {
+        Ok(r) => r,
+        Err(e) => {
+            log::error!("{e}");
+            return None;
+        }
+    };
+    if p.params_off() == 0 {
+        Some(Signature::new(&t, &n, None, &r))
+    } else {
+        match dex.get_interfaces(p.params_off()) {
+            Ok(params) => Some(Signature::new(&t, &n, Some(&params), &r)),
+            Err(e) => {
+                log::error!("{e}");
+                None
+            }
+        }
+    }
 }
 
 #[cfg(test)]
@@ -133,12 +234,29 @@ mod tests {
     use crate::dex::{instruction::Instruction, method::Signature, Opcode};
     use dex::DexReader;
 
-    use super::get_methods;
+    use super::{get_methods, is_entry_point};
+    use crate::options::ParseOptions;
+    use std::collections::HashSet;
+
+    #[test]
+    fn test_is_entry_point() {
+        let entry_points = HashSet::from([
+            "Lcom/example/Main;".to_string(),
+            "Lcom/example/Outer$Inner;".to_string(),
+        ]);
+        assert!(is_entry_point(&entry_points, "Lcom/example/Main;"));
+        assert!(is_entry_point(&entry_points, "Lcom/example/Main$1;"));
+        assert!(is_entry_point(&entry_points, "Lcom/example/Main$Foo$Bar;"));
+        assert!(is_entry_point(&entry_points, "Lcom/example/Outer$Inner$1;"));
+        assert!(!is_entry_point(&entry_points, "Lcom/example/Outer;"));
+        assert!(!is_entry_point(&entry_points, "Lcom/example/MainActivity;"));
+        assert!(!is_entry_point(&entry_points, "Lcom/example/main/Main;"));
+    }
 
     #[test]
     fn test_hello_world() {
         let dex = DexReader::from_file("tests/dex/hello_world.dex").unwrap();
-        let methods = get_methods(&[dex], None).unwrap();
+        let (methods, ..) = get_methods([(None, &dex)], None, &ParseOptions::default()).unwrap();
 
         let init = &methods[0];
         assert_eq!(
@@ -200,7 +318,7 @@ mod tests {
     #[test]
     fn test_call_graph() {
         let dex = DexReader::from_file("tests/dex/call_graph.dex").unwrap();
-        let methods = get_methods(&[dex], None).unwrap();
+        let (methods, ..) = get_methods([(None, &dex)], None, &ParseOptions::default()).unwrap();
         assert_eq!(
             methods[0].signature,
             Signature {
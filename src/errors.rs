@@ -1,17 +1,22 @@
-use axmldecoder::ParseError;
 use thiserror::Error;
 use zip::result::ZipError;
 
-use crate::dex::DexError;
+use crate::{dex::DexError, xml::errors::XmlError};
 
 #[derive(Debug, Error)]
 pub enum ApkParseError {
     #[error("Failed to read archive: {0}")]
     ZipError(ZipError),
     #[error("Failed to parse manifest: {0}")]
-    ManifestError(ParseError),
+    ManifestError(XmlError),
+    #[error("Failed to parse XML file {path}: {source}")]
+    XmlError { path: String, source: XmlError },
     #[error("Failed to parse DEX file: {0}")]
     DexError(DexError),
+    #[error("Failed to read DEX file: {0}")]
+    DexReadError(::dex::Error),
+    #[error("No APK found in the bundle")]
+    NoApk,
 }
 
 impl From<ZipError> for ApkParseError {
@@ -20,8 +25,8 @@ impl From<ZipError> for ApkParseError {
     }
 }
 
-impl From<ParseError> for ApkParseError {
-    fn from(e: ParseError) -> Self {
+impl From<XmlError> for ApkParseError {
+    fn from(e: XmlError) -> Self {
         ApkParseError::ManifestError(e)
     }
 }
@@ -31,3 +36,9 @@ impl From<DexError> for ApkParseError {
         ApkParseError::DexError(e)
     }
 }
+
+impl From<::dex::Error> for ApkParseError {
+    fn from(e: ::dex::Error) -> Self {
+        ApkParseError::DexReadError(e)
+    }
+}
@@ -0,0 +1,36 @@
+use serde::Serialize;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize)]
+pub enum Severity {
+    Info,
+    Low,
+    Medium,
+    High,
+}
+
+/// An issue found by one of the analyses (e.g. the network security config)
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Finding {
+    /// Identifier of the rule that raised the finding (e.g. `nsc-cleartext-global`)
+    pub rule: String,
+    pub severity: Severity,
+    /// Where the issue is (e.g. `domain-config[example.com]` or `activity com.example.Main`)
+    pub location: String,
+    pub message: String,
+}
+
+impl Finding {
+    pub(crate) fn new(
+        rule: &str,
+        severity: Severity,
+        location: impl Into<String>,
+        message: impl Into<String>,
+    ) -> Self {
+        Finding {
+            rule: rule.to_string(),
+            severity,
+            location: location.into(),
+            message: message.into(),
+        }
+    }
+}
@@ -0,0 +1,189 @@
+use serde::Serialize;
+
+use std::collections::HashMap;
+
+use crate::{
+    dex::{enclosing_classes, IccKind, IntentSend, IntentTarget, Signature},
+    manifest::{component::Component, Manifest},
+};
+
+/// Inter-component communication between two components of the manifest
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct IccEdge {
+    /// Name of the sending component, `None` if the sending method is not part of one
+    pub source: Option<String>,
+    /// Name of the receiving component, see `Component::name`.
+    /// Intents resolved by an activity alias are credited to its target activity.
+    pub target: String,
+    pub kind: IccKind,
+    /// Method sending the intent
+    #[serde(skip)]
+    pub method: Signature,
+    pub intent: IntentTarget,
+}
+
+/// Links the intents sent from the DEX(es) to the components declared in the manifest.
+///
+/// Explicit intents are matched by class, implicit ones by the actions of the intent filters.
+/// Intents without a matching component (e.g. sent to other apps) are dropped.
+pub(crate) fn link(manifest: &Manifest, sends: &[IntentSend]) -> Vec<IccEdge> {
+    let by_descriptor: HashMap<_, _> = manifest
+        .components()
+        .map(|c| (c.descriptor.as_str(), c))
+        .collect();
+
+    let mut edges = Vec::new();
+    for send in sends {
+        // The innermost component the sending class is or is nested in
+        let source = enclosing_classes(&send.method.class_type)
+            .find_map(|class| by_descriptor.get(class.as_str()))
+            .map(|c| c.name.clone());
+
+        // Each candidate with the name of the component it is credited to
+        let candidates: Vec<(&Component, &str)> = match send.kind {
+            IccKind::Activity => manifest
+                .activities
+                .iter()
+                .map(|c| (c, c.name.as_str()))
+                .chain(
+                    manifest
+                        .activity_aliases
+                        .iter()
+                        .filter_map(|a| Some((a, a.target_activity.as_deref()?))),
+                )
+                .collect(),
+            IccKind::Service => manifest
+                .services
+                .iter()
+                .map(|c| (c, c.name.as_str()))
+                .collect(),
+            IccKind::Broadcast => manifest
+                .receivers
+                .iter()
+                .map(|c| (c, c.name.as_str()))
+                .collect(),
+        };
+        let matches = |(c, _): &(&Component, &str)| match &send.target {
+            IntentTarget::Class(class) => c.descriptor == *class,
+            IntentTarget::Action(action) => {
+                c.intent_filters.iter().any(|f| f.actions.contains(action))
+            }
+        };
+        for (_, target) in candidates.into_iter().filter(matches) {
+            let edge = IccEdge {
+                source: source.clone(),
+                target: target.to_string(),
+                kind: send.kind,
+                method: send.method.clone(),
+                intent: send.target.clone(),
+            };
+            if !edges.contains(&edge) {
+                edges.push(edge);
+            }
+        }
+    }
+    edges
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        dex::{IccKind, IntentSend, IntentTarget, Signature},
+        manifest::{component::Component, intent_filter::IntentFilter, Manifest},
+    };
+
+    use super::link;
+
+    fn component(name: &str, actions: &[&str]) -> Component {
+        let mut component = Component {
+            declared_name: name.into(),
+            intent_filters: vec![IntentFilter {
+                actions: actions.iter().map(|a| a.to_string()).collect(),
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+        component.resolve(Some("com.example"));
+        component
+    }
+
+    fn send(class_type: &str, kind: IccKind, target: IntentTarget) -> IntentSend {
+        IntentSend {
+            method: Signature {
+                class_type: class_type.into(),
+                method_name: "onClick".into(),
+                params: None,
+                return_type: "V".into(),
+            },
+            kind,
+            target,
+        }
+    }
+
+    #[test]
+    fn test_link() {
+        let mut alias = component(".Share", &["android.intent.action.SEND"]);
+        alias.target_activity = Some("com.example.Second".into());
+        let manifest = Manifest {
+            package: Some("com.example".into()),
+            activities: vec![
+                component(".Main", &[]),
+                component("com.example.Second", &[]),
+                component(".ui.Outer$Inner", &[]),
+            ],
+            activity_aliases: vec![alias],
+            receivers: vec![component(".Ping", &["com.example.PING"])],
+            ..Default::default()
+        };
+        let sends = [
+            send(
+                "Lcom/example/Main$1;",
+                IccKind::Activity,
+                IntentTarget::Class("Lcom/example/Second;".into()),
+            ),
+            send(
+                "Lcom/example/Main$1;",
+                IccKind::Broadcast,
+                IntentTarget::Action("com.example.PING".into()),
+            ),
+            send(
+                "Lcom/example/Main$1;",
+                IccKind::Activity,
+                IntentTarget::Class("Lcom/other/Activity;".into()),
+            ),
+            send(
+                "Lcom/example/ui/Outer$Inner$1;",
+                IccKind::Activity,
+                IntentTarget::Action("android.intent.action.SEND".into()),
+            ),
+        ];
+
+        let edges: Vec<_> = link(&manifest, &sends)
+            .into_iter()
+            .map(|e| (e.source, e.target, e.kind, e.intent))
+            .collect();
+        assert_eq!(
+            edges,
+            vec![
+                (
+                    Some("com.example.Main".into()),
+                    "com.example.Second".into(),
+                    IccKind::Activity,
+                    IntentTarget::Class("Lcom/example/Second;".into()),
+                ),
+                (
+                    Some("com.example.Main".into()),
+                    "com.example.Ping".into(),
+                    IccKind::Broadcast,
+                    IntentTarget::Action("com.example.PING".into()),
+                ),
+                (
+                    Some("com.example.ui.Outer$Inner".into()),
+                    "com.example.Second".into(),
+                    IccKind::Activity,
+                    IntentTarget::Action("android.intent.action.SEND".into()),
+                ),
+            ]
+        );
+    }
+}
@@ -2,29 +2,83 @@
 extern crate lazy_static;
 
 mod apk;
+mod archive;
 mod dex;
 mod errors;
+mod finding;
+mod icc;
+mod lint;
 mod manifest;
+mod network_security;
+mod options;
+mod resources;
+mod signing;
+mod xml;
 
-use ::dex::DexReader;
-use dex::get_methods;
-use regex::{bytes::Regex as BytesRegex, Regex};
-use std::io::{Read, Seek};
-use zip::ZipArchive;
+use ::dex::Dex;
+use dex::{get_methods, is_dex, read_dex};
+use regex::Regex;
+use std::{
+    collections::{BTreeMap, HashSet},
+    io::{Cursor, Read, Seek},
+};
+use zip::{result::ZipError, ZipArchive};
 
 pub use apk::Apk;
+pub use archive::{ArchiveEntry, ContentType, Embedded};
+pub use dex::{
+    CallGraph, CallbackModel, CallbackRule, Condensation, Edge, EdgeKind, IccKind, IntentSend,
+    IntentTarget, Signature,
+};
 pub use errors::ApkParseError;
+pub use finding::{Finding, Severity};
+pub use icc::IccEdge;
+pub use lint::{LintRule, Linter, Violations};
+pub use manifest::{
+    application::{Application, InstallLocation, Instrumentation, UsesSdk},
+    component::{Component, ComponentKind, LaunchMode},
+    feature::{UsesFeature, UsesLibrary},
+    intent_filter::{IntentData, IntentFilter},
+    metadata::{Metadata, MetadataValue},
+    permission::{
+        DeclarationKind, PermissionDeclaration, ProtectionBase, ProtectionLevel, UsesPermission,
+    },
+    queries::Queries,
+    value::{ResourceRef, TextValue},
+    Manifest,
+};
+pub use network_security::{
+    CertificateSource, Certificates, Domain, DomainConfig, NetworkSecurityConfig, Pin, PinSet,
+};
+pub use options::{MethodOrder, ParseOptions};
+pub use resources::{
+    config::Config, errors::ResourceError, value::ResValue, Entry, EntryValue, Package,
+    ResolvedValue, ResourceTable, ResourceType, TypeConfig,
+};
+pub use signing::{
+    certificate::Certificate, errors::SigningError, LineageNode, Signatures, Signer, Verdict,
+    Verification,
+};
+pub use xml::{errors::XmlError, parse_xml, XmlElement, XmlValue};
 
 lazy_static! {
-    static ref DEX_MAGIC: BytesRegex =
-        BytesRegex::new(r"\x64\x65\x78\x0A\x30\x33[\x35-\x39]\x00").unwrap();
+    static ref CLASSES_DEX: Regex = Regex::new(r"^classes\d*\.dex$").unwrap();
 }
 
+/// Manifest of the base module of an Android App Bundle, in protobuf XML
+const BUNDLE_MANIFEST: &str = "base/manifest/AndroidManifest.xml";
+
+/// A DEX file read from an archive, with the split APK or bundle module it belongs to
+type DexFile = (Option<String>, Dex<Vec<u8>>);
+
 /// Parses a source of bytes (e.g., a .apk archive) into an `Apk` structure.
 ///
 /// This function reads an APK archive, extracting its manifest and DEX (Dalvik Executable) files,
 /// and constructs an `Apk` with a sorted list of methods.
 ///
+/// Android App Bundles (`.aab`) are read as well, with the protobuf manifests and the DEX files of
+/// every module, the modules besides `base` being reported like split APKs (see [`parse_splits`]).
+///
 /// ### Arguments
 /// * `apk`: A reader and seeker that represents the apk archive.
 ///
@@ -43,68 +97,967 @@ lazy_static! {
 ///     println!("{compact:?}");
 /// }
 /// ```
-pub fn parse<'a, R: Read + Seek>(apk: R) -> Result<Apk, ApkParseError> {
-    let mut zip_archive = ZipArchive::new(apk)?;
-    let mut manifest = None;
-    let mut dexes = Vec::new();
+pub fn parse<R: Read + Seek>(apk: R) -> Result<Apk, ApkParseError> {
+    ParseOptions::default().parse(apk)
+}
+
+/// Parses an apk like [`parse`], linking implicit framework callbacks with the given model.
+///
+/// ### Arguments
+/// * `apk`: A reader and seeker that represents the apk archive.
+/// * `callbacks`: Framework APIs that run callbacks (e.g. `Thread.start` runs `run`),
+///   use `CallbackModel::empty()` to keep only the explicit calls.
+///
+/// ### Example
+/// ```no_run
+/// use dexompiler::{parse_with_callbacks, CallbackModel, CallbackRule};
+///
+/// let model = CallbackModel::default().with_rule(CallbackRule::new(
+///     &["Lcom/example/Bus;"],
+///     &["subscribe"],
+///     &["onEvent"],
+/// ));
+/// let file = std::fs::File::open("tests/example.apk").unwrap();
+/// let apk = parse_with_callbacks(file, &model).unwrap();
+/// println!("{}", apk.call_graph.len());
+/// ```
+pub fn parse_with_callbacks<R: Read + Seek>(
+    apk: R,
+    callbacks: &CallbackModel,
+) -> Result<Apk, ApkParseError> {
+    ParseOptions::new().callbacks(callbacks.clone()).parse(apk)
+}
+
+/// Analyses a DEX file on its own, the resulting `Apk` has no manifest.
+///
+/// ### Example
+/// ```no_run
+/// use dexompiler::parse_dex;
+///
+/// let dex = std::fs::read("tests/classes.dex").unwrap();
+/// let apk = parse_dex(&dex).unwrap();
+/// println!("{} methods", apk.methods.len());
+/// ```
+pub fn parse_dex(dex: &[u8]) -> Result<Apk, ApkParseError> {
+    parse_dexes(&[dex])
+}
+
+/// Analyses several DEX files as a whole (e.g. the `classes*.dex` of a multidex app),
+/// the resulting `Apk` has no manifest.
+pub fn parse_dexes<B: AsRef<[u8]>>(dexes: &[B]) -> Result<Apk, ApkParseError> {
+    ParseOptions::default().parse_dexes(dexes)
+}
+
+/// Analyses the DEX files of a JAR or an AAR (Android library), e.g. an SDK on its own.
+///
+/// Every DEX file of the archive is analysed, along with the ones of the JARs it contains
+/// (`classes.jar` and `libs/*.jar` of an AAR). Java bytecode (`.class` files) is not, and the
+/// resulting `Apk` has no manifest.
+pub fn parse_library<R: Read + Seek>(library: R) -> Result<Apk, ApkParseError> {
+    ParseOptions::default().parse_library(library)
+}
+
+/// Parses a split APK set into a single `Apk` like [`parse_splits`].
+///
+/// Supports the `.apks` archives of bundletool (the APKs under `splits/`, or `universal.apk`)
+/// and the `.xapk` archives of mirror sites (the APKs at the root).
+///
+/// ### Example
+/// ```no_run
+/// use dexompiler::parse_bundle;
+///
+/// let file = std::fs::File::open("tests/example.apks").unwrap();
+/// let apk = parse_bundle(file).unwrap();
+/// for method in apk.methods.iter().filter(|m| m.split.is_some()) {
+///     println!("{:?} in {:?}", method.signature, method.split);
+/// }
+/// ```
+pub fn parse_bundle<R: Read + Seek>(bundle: R) -> Result<Apk, ApkParseError> {
+    ParseOptions::default().parse_bundle(bundle)
+}
+
+/// Parses the base and split APKs of an app (e.g. the ones `adb install-multiple` takes) into a
+/// single `Apk`.
+///
+/// The code of every APK is analysed as a whole and the components of the splits are added to
+/// the manifest of the base APK, `Method::split` and `Component::split` tell where they come
+/// from. The resources of the splits (e.g. the strings of a language split) are added to the
+/// ones of the base APK, signatures and entries are the ones of the base APK only.
+///
+/// ### Arguments
+/// * `apks`: The APKs of the app, in any order.
+pub fn parse_splits<R: Read + Seek>(
+    apks: impl IntoIterator<Item = R>,
+) -> Result<Apk, ApkParseError> {
+    ParseOptions::default().parse_splits(apks)
+}
+
+pub(crate) fn parse_library_archive<R: Read + Seek>(
+    library: R,
+    options: &ParseOptions,
+) -> Result<Apk, ApkParseError> {
+    let mut zip_archive = ZipArchive::new(library)?;
     let mut files = Vec::with_capacity(zip_archive.len());
+    let mut meta_inf = BTreeMap::new();
+    let mut bufs = Vec::new();
+    let extract = |name: &str| {
+        !options.skip_code
+            || !options.skip_hashes
+            || (!options.skip_signatures && name.starts_with("META-INF/"))
+    };
 
     for i in 0..zip_archive.len() {
-        let mut file = match zip_archive.by_index(i) {
-            Ok(file) => file,
+        let Some((entry, buf)) = read_entry(&mut zip_archive, i, extract, !options.skip_hashes)
+        else {
+            continue;
+        };
+        let name = entry.name.clone();
+        let content_type = entry.content_type;
+        files.push(entry);
+        let Some(buf) = buf else {
+            continue;
+        };
+
+        match content_type {
+            ContentType::Dex | ContentType::Jar | ContentType::Zip if options.skip_code => {}
+            ContentType::Dex => bufs.push(buf),
+            ContentType::Jar | ContentType::Zip => bufs.extend(nested_dexes(&name, buf)),
+            _ if name.starts_with("META-INF/") => {
+                meta_inf.insert(name, buf);
+            }
+            _ => {}
+        }
+    }
+    if bufs.is_empty() && !options.skip_code {
+        log::warn!("No DEX file found in the library");
+    }
+
+    let dexes = read_dexes(bufs, options)?;
+    let (signatures, verification) = if options.skip_signatures {
+        Default::default()
+    } else {
+        signing::read(zip_archive, &meta_inf)
+    };
+    let apk = Apk {
+        signatures,
+        verification,
+        files,
+        ..Default::default()
+    };
+    analyse(apk, &dexes, options)
+}
+
+pub(crate) fn parse_split_bundle<R: Read + Seek>(
+    bundle: R,
+    options: &ParseOptions,
+) -> Result<Apk, ApkParseError> {
+    let mut zip_archive = ZipArchive::new(bundle)?;
+    let names: Vec<_> = zip_archive
+        .file_names()
+        .filter(|name| name.ends_with(".apk"))
+        .map(str::to_string)
+        .collect();
+    // Standalone APKs of bundletool duplicate the splits for devices without split support
+    let in_splits = names.iter().any(|name| name.starts_with("splits/"));
+    let mut apks = Vec::new();
+    for name in names {
+        if in_splits && !name.starts_with("splits/") {
+            continue;
+        }
+        let mut buf = Vec::new();
+        zip_archive
+            .by_name(&name)?
+            .read_to_end(&mut buf)
+            .map_err(ZipError::from)?;
+        apks.push(Cursor::new(buf));
+    }
+    parse_split_archives(apks, options)
+}
+
+pub(crate) fn parse_split_archives<R: Read + Seek>(
+    apks: impl IntoIterator<Item = R>,
+    options: &ParseOptions,
+) -> Result<Apk, ApkParseError> {
+    let mut base = None;
+    let mut splits = Vec::new();
+    let mut dexes = Vec::new();
+    for mut apk in apks {
+        // Without the manifests the `split` attribute is still needed to tell the base apart
+        let split = if options.skip_manifest {
+            read_split(&mut apk)
+        } else {
+            Ok(None)
+        };
+        let read = split.and_then(|split| Ok((split, read_archive(apk, options, 0)?)));
+        let (split, (apk, apk_dexes)) = match read {
+            Ok(read) => read,
             Err(e) => {
-                log::error!("Error reading file at index {i}: {e}");
+                options.recover(e)?;
                 continue;
             }
         };
+        let split = split.or_else(|| apk.manifest.as_ref().and_then(|m| m.split.clone()));
+        dexes.extend(apk_dexes.into_iter().map(|(_, dex)| (split.clone(), dex)));
+        if split.is_none() && base.is_none() {
+            base = Some(apk);
+        } else {
+            splits.push(apk);
+        }
+    }
+
+    let mut base = match base {
+        Some(base) => base,
+        None if !splits.is_empty() => {
+            log::warn!("No base APK found, merging the splits into the first one");
+            splits.remove(0)
+        }
+        None => return Err(ApkParseError::NoApk),
+    };
+    for split in splits {
+        match (base.manifest.as_mut(), split.manifest) {
+            (Some(manifest), Some(split_manifest)) => manifest.merge(split_manifest),
+            (None, split_manifest) => base.manifest = split_manifest,
+            _ => {}
+        }
+        match (base.resources.as_mut(), split.resources) {
+            (Some(resources), Some(split_resources)) => resources.merge(split_resources),
+            (None, split_resources) => base.resources = split_resources,
+            _ => {}
+        }
+        base.embedded.extend(split.embedded);
+    }
+    analyse(base, &dexes, options)
+}
 
-        files.push(file.name().into());
+/// `split` attribute of the manifest of an APK, `None` for a base APK or without a manifest
+fn read_split<R: Read + Seek>(apk: &mut R) -> Result<Option<String>, ApkParseError> {
+    let mut zip_archive = ZipArchive::new(apk)?;
+    let mut file = match zip_archive.by_name("AndroidManifest.xml") {
+        Ok(file) => file,
+        Err(ZipError::FileNotFound) => return Ok(None),
+        Err(e) => return Err(e.into()),
+    };
+    let mut buf = Vec::new();
+    file.read_to_end(&mut buf).map_err(ZipError::from)?;
+    Ok(manifest::parse(&buf)?.and_then(|m| m.split))
+}
 
-        let mut buf = Vec::new();
-        if let Err(e) = file.read_to_end(&mut buf) {
-            log::warn!("Error reading file: {e}");
+/// Parses the archive at `depth`, analysing its embedded payloads while under the maximum depth
+pub(crate) fn parse_archive<R: Read + Seek>(
+    apk: R,
+    options: &ParseOptions,
+    depth: usize,
+) -> Result<Apk, ApkParseError> {
+    let (apk, dexes) = read_archive(apk, options, depth)?;
+    analyse(apk, &dexes, options)
+}
+
+/// Reads everything but the code of the archive, returned as the DEX files to analyse
+fn read_archive<R: Read + Seek>(
+    apk: R,
+    options: &ParseOptions,
+    depth: usize,
+) -> Result<(Apk, Vec<DexFile>), ApkParseError> {
+    let mut zip_archive = ZipArchive::new(apk)?;
+    let bundle = zip_archive.index_for_name(BUNDLE_MANIFEST).is_some();
+    let mut manifest = None;
+    let mut module_manifests = Vec::new();
+    let mut dexes = Vec::new();
+    let mut resources = None;
+    let mut files = Vec::with_capacity(zip_archive.len());
+    let mut meta_inf = BTreeMap::new();
+    let mut payloads = Vec::new();
+    // Without code, hashes nor signatures only the manifest and resources are needed
+    let extract = |name: &str| {
+        !options.skip_code
+            || !options.skip_hashes
+            || name == "AndroidManifest.xml"
+            || name == "resources.arsc"
+            || (bundle && name.ends_with("/manifest/AndroidManifest.xml"))
+            || (!options.skip_signatures && name.starts_with("META-INF/"))
+    };
+
+    for i in 0..zip_archive.len() {
+        let Some((entry, buf)) = read_entry(&mut zip_archive, i, extract, !options.skip_hashes)
+        else {
             continue;
-        }
+        };
+        let name = entry.name.clone();
+        let content_type = entry.content_type;
+        files.push(entry);
+        let Some(buf) = buf else {
+            continue;
+        };
+        // Entries of an App Bundle are in the directory of their module (e.g. `base/dex/`),
+        // the base module is not tagged like the base APK of a split APK set
+        let (split, dex_name) = match name.split_once('/') {
+            Some((module, path)) if bundle => (
+                Some(module).filter(|&m| m != "base").map(str::to_string),
+                path.strip_prefix("dex/"),
+            ),
+            _ => (None, Some(name.as_str())),
+        };
 
-        if file.name() == "AndroidManifest.xml" {
+        if name == "AndroidManifest.xml" && !options.skip_manifest {
             if manifest.is_some() {
                 log::warn!("Multiple AndroidManifest.xml files found in APK");
             } else {
-                manifest = manifest::parse(&buf)?;
-            }
-        } else {
-            if DEX_MAGIC.is_match(&buf) {
-                match DexReader::from_vec(buf) {
-                    Ok(dex) => dexes.push(dex),
-                    Err(e) => log::error!("{e}"),
+                match manifest::parse(&buf) {
+                    Ok(parsed) => manifest = parsed,
+                    Err(e) => options.recover(e)?,
                 }
             }
+        } else if bundle
+            && name.ends_with("/manifest/AndroidManifest.xml")
+            && !options.skip_manifest
+        {
+            match manifest::parse_proto(&buf) {
+                Ok(parsed) => module_manifests.extend(parsed.map(|m| (split, m))),
+                Err(e) => options.recover(e)?,
+            }
+        } else if name == "resources.arsc" {
+            match ResourceTable::parse(&buf) {
+                Ok(table) => resources = Some(table),
+                Err(e) => log::error!("Failed to parse resources.arsc: {e}"),
+            }
+        } else if dex_name.is_some_and(|n| CLASSES_DEX.is_match(n)) && is_dex(&buf) {
+            if options.skip_code {
+                continue;
+            }
+            match read_dex(buf) {
+                Ok(sections) => dexes.extend(sections.into_iter().map(|d| (split.clone(), d))),
+                Err(e) => options.recover_dex(e)?,
+            }
+        } else if name.starts_with("META-INF/") {
+            meta_inf.insert(name, buf);
+        } else if !options.skip_code
+            && matches!(
+                content_type,
+                ContentType::Dex | ContentType::Zip | ContentType::Jar
+            )
+        {
+            if depth < options.max_depth {
+                payloads.push((name, content_type, buf));
+            } else {
+                log::warn!(
+                    "Skipping {name}, embedded deeper than {} archives",
+                    options.max_depth
+                );
+            }
         }
     }
 
-    let regexes = if let Some(ref m) = manifest {
-        match m
-            .activities
-            .iter()
-            .chain(m.services.iter())
-            .chain(m.receivers.iter())
-            .chain(m.providers.iter())
-            .map(|s| Regex::new(s.name.replace('.', "/").as_str()))
-            .collect::<Result<Vec<_>, _>>()
-        {
-            Ok(regexes) => Some(regexes),
-            Err(e) => {
-                log::error!("{e}");
-                None
+    // Manifests of the feature modules are merged into the one of the base module
+    module_manifests.sort_by_key(|(split, _)| split.is_some());
+    for (split, mut module_manifest) in module_manifests {
+        match manifest.as_mut() {
+            Some(manifest) => {
+                if module_manifest.split.is_none() {
+                    module_manifest.split = split;
+                }
+                manifest.merge(module_manifest);
             }
+            None => manifest = Some(module_manifest),
         }
+    }
+
+    let network_security_config = manifest
+        .as_ref()
+        .and_then(|m| m.application.network_security_config.as_ref())
+        .and_then(|r| network_security::path(r, resources.as_ref()))
+        .and_then(|path| read_network_security_config(&mut zip_archive, &path, resources.as_ref()));
+    let (signatures, verification) = if options.skip_signatures {
+        Default::default()
     } else {
-        None
+        signing::read(zip_archive, &meta_inf)
     };
 
-    Ok(Apk {
+    let embedded = payloads
+        .into_iter()
+        .filter_map(|(path, content_type, buf)| {
+            let apk = match content_type {
+                ContentType::Dex => read_dexes([buf], options)
+                    .and_then(|dexes| analyse(Apk::default(), &dexes, options)),
+                _ => parse_archive(Cursor::new(buf), options, depth + 1),
+            };
+            match apk {
+                Ok(apk) => Some(Embedded {
+                    path,
+                    content_type,
+                    apk,
+                }),
+                Err(e) => {
+                    log::warn!("Failed to analyse embedded {path}: {e}");
+                    None
+                }
+            }
+        })
+        .collect();
+    let apk = Apk {
         manifest,
-        methods: get_methods(&dexes, regexes)?,
+        resources,
+        network_security_config,
+        signatures,
+        verification,
         files,
-    })
+        embedded,
+        ..Default::default()
+    };
+    Ok((apk, dexes))
+}
+
+/// Extracts the methods of `dexes`, tagged with their split, prioritizing the components of
+/// the manifest of `apk`
+pub(crate) fn analyse(
+    mut apk: Apk,
+    dexes: &[DexFile],
+    options: &ParseOptions,
+) -> Result<Apk, ApkParseError> {
+    let entry_points = apk.manifest.as_ref().map(|m| {
+        m.components()
+            .map(|c| c.descriptor.clone())
+            // Aliases make their target activity an entry point as well
+            .chain(
+                m.activity_aliases
+                    .iter()
+                    .filter_map(|a| a.target_activity.as_ref())
+                    .map(|t| m.class_descriptor(t)),
+            )
+            .collect::<HashSet<_>>()
+    });
+
+    let (methods, call_graph, intents) = get_methods(
+        dexes.iter().map(|(split, dex)| (split.as_deref(), dex)),
+        entry_points.as_ref(),
+        options,
+    )?;
+    apk.icc = apk
+        .manifest
+        .as_ref()
+        .map(|m| icc::link(m, &intents))
+        .unwrap_or_default();
+    apk.methods = methods;
+    apk.call_graph = call_graph;
+    apk.intents = intents;
+    if options.order == MethodOrder::Topological {
+        apk.sort_topologically();
+    }
+    if let Some(max) = options.max_methods {
+        apk.methods.truncate(max);
+    }
+    Ok(apk)
+}
+
+/// Reads DEX files found outside of the `classes*.dex` of an app, they belong to no split
+pub(crate) fn read_dexes(
+    bufs: impl IntoIterator<Item = Vec<u8>>,
+    options: &ParseOptions,
+) -> Result<Vec<DexFile>, ApkParseError> {
+    let mut dexes = Vec::new();
+    for buf in bufs {
+        match read_dex(buf) {
+            Ok(sections) => dexes.extend(sections.into_iter().map(|dex| (None, dex))),
+            Err(e) => options.recover(e)?,
+        }
+    }
+    Ok(dexes)
+}
+
+/// DEX files of a JAR nested in a library, logging any error
+fn nested_dexes(name: &str, buf: Vec<u8>) -> Vec<Vec<u8>> {
+    let mut zip_archive = match ZipArchive::new(Cursor::new(buf)) {
+        Ok(zip_archive) => zip_archive,
+        Err(e) => {
+            log::warn!("Failed to read {name}: {e}");
+            return Vec::new();
+        }
+    };
+    (0..zip_archive.len())
+        .filter_map(|i| read_entry(&mut zip_archive, i, |_| true, false))
+        .filter_map(|(entry, buf)| buf.filter(|_| entry.content_type == ContentType::Dex))
+        .collect()
+}
+
+/// Reads the entry at `index` with its metadata, the content is `None` if `extract` is false
+/// for its name or if it cannot be extracted (e.g. an encrypted entry)
+///
+/// ### Arguments
+/// * `extract`: Whether to extract the entry with the given name.
+/// * `hash`: Whether to compute the SHA-256 of the content, see `ArchiveEntry::sha256`.
+fn read_entry<R: Read + Seek>(
+    zip_archive: &mut ZipArchive<R>,
+    index: usize,
+    extract: impl Fn(&str) -> bool,
+    hash: bool,
+) -> Option<(ArchiveEntry, Option<Vec<u8>>)> {
+    if let Ok(file) = zip_archive.by_index_raw(index) {
+        if !extract(file.name()) {
+            return Some((ArchiveEntry::new(&file, None, hash), None));
+        }
+    }
+    match zip_archive.by_index(index) {
+        Ok(mut file) => {
+            let mut buf = Vec::new();
+            return match file.read_to_end(&mut buf) {
+                Ok(_) => Some((ArchiveEntry::new(&file, Some(&buf), hash), Some(buf))),
+                Err(e) => {
+                    log::warn!("Error reading file: {e}");
+                    Some((ArchiveEntry::new(&file, None, hash), None))
+                }
+            };
+        }
+        Err(e) => log::error!("Error reading file at index {index}: {e}"),
+    }
+    zip_archive
+        .by_index_raw(index)
+        .ok()
+        .map(|file| (ArchiveEntry::new(&file, None, hash), None))
+}
+
+/// Reads the network security config at `path`, logging any error
+fn read_network_security_config<R: Read + Seek>(
+    zip_archive: &mut ZipArchive<R>,
+    path: &str,
+    resources: Option<&ResourceTable>,
+) -> Option<NetworkSecurityConfig> {
+    let mut file = match zip_archive.by_name(path) {
+        Ok(file) => file,
+        Err(e) => {
+            log::warn!("Error reading network security config {path}: {e}");
+            return None;
+        }
+    };
+    let mut buf = Vec::new();
+    if let Err(e) = file.read_to_end(&mut buf) {
+        log::warn!("Error reading network security config {path}: {e}");
+        return None;
+    }
+    match parse_xml(&buf, resources) {
+        Ok(root) => root.as_ref().map(NetworkSecurityConfig::from),
+        Err(e) => {
+            log::warn!("Error parsing network security config {path}: {e}");
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        collections::HashSet,
+        fs,
+        io::{Cursor, Write},
+    };
+
+    use zip::{write::SimpleFileOptions, ZipWriter};
+
+    use super::{
+        parse, parse_bundle, parse_dex, parse_dexes, parse_library, parse_splits, ApkParseError,
+        ContentType, Metadata, MetadataValue, MethodOrder, ParseOptions,
+    };
+
+    fn zip(entries: &[(&str, &[u8])]) -> Vec<u8> {
+        let mut writer = ZipWriter::new(Cursor::new(Vec::new()));
+        for (name, content) in entries {
+            writer
+                .start_file(*name, SimpleFileOptions::default())
+                .unwrap();
+            writer.write_all(content).unwrap();
+        }
+        writer.finish().unwrap().into_inner()
+    }
+
+    #[test]
+    fn test_embedded() {
+        let inner = fs::read("tests/apk/signed.apk").unwrap();
+        let dex = fs::read("tests/dex/hello_world.dex").unwrap();
+        let outer = zip(&[
+            ("assets/inner.apk", &inner),
+            ("res/raw/payload", &dex),
+            ("res/raw/hello.txt", b"hello\n"),
+        ]);
+
+        let apk = parse(Cursor::new(outer)).unwrap();
+        assert!(apk.manifest.is_none());
+        assert!(apk.methods.is_empty());
+        assert_eq!(apk.embedded.len(), 2);
+
+        let embedded = &apk.embedded[0];
+        assert_eq!(embedded.path, "assets/inner.apk");
+        assert_eq!(embedded.content_type, ContentType::Zip);
+        assert!(embedded.apk.manifest.is_some());
+        assert_eq!(embedded.apk.methods.len(), 2);
+        assert!(embedded.apk.verification.is_verified());
+
+        let embedded = &apk.embedded[1];
+        assert_eq!(embedded.path, "res/raw/payload");
+        assert_eq!(embedded.content_type, ContentType::Dex);
+        assert!(embedded.apk.manifest.is_none());
+        assert_eq!(embedded.apk.methods[1].signature.method_name, "main");
+    }
+
+    #[test]
+    fn test_max_depth() {
+        for (options, depth) in [
+            (ParseOptions::default(), 3),
+            (ParseOptions::new().max_depth(1), 1),
+        ] {
+            let mut archive = zip(&[(
+                "classes.dex",
+                &fs::read("tests/dex/hello_world.dex").unwrap(),
+            )]);
+            for _ in 0..=depth {
+                archive = zip(&[("assets/nested.apk", &archive)]);
+            }
+
+            let mut apk = &options.parse(Cursor::new(archive)).unwrap();
+            for _ in 0..depth {
+                assert_eq!(apk.embedded.len(), 1);
+                apk = &apk.embedded[0].apk;
+            }
+            assert!(apk.embedded.is_empty());
+            assert!(apk.methods.is_empty());
+            assert_eq!(apk.files[0].name, "assets/nested.apk");
+        }
+    }
+
+    fn split_apk(split: &str, activity: &str) -> Vec<u8> {
+        let manifest = format!(
+            r#"<manifest xmlns:android="http://schemas.android.com/apk/res/android"
+                package="com.test.dexompiler" split="{split}">
+              <uses-permission android:name="android.permission.INTERNET"/>
+              <uses-permission android:name="android.permission.CAMERA"/>
+              <permission android:name="com.test.permission.{split}"/>
+              <uses-feature android:name="android.hardware.camera"/>
+              <uses-feature android:name="android.hardware.camera"/>
+              <queries>
+                <package android:name="com.test.{split}"/>
+              </queries>
+              <application>
+                <uses-library android:name="com.test.{split}"/>
+                <meta-data android:name="com.test.{split}" android:value="true"/>
+                <activity android:name="{activity}"/>
+                <activity android:name="com.test.TestActivity"/>
+              </application>
+            </manifest>"#
+        );
+        let dex = fs::read("tests/dex/call_graph.dex").unwrap();
+        let resources = fs::read("tests/resources/a.arsc").unwrap();
+        zip(&[
+            ("AndroidManifest.xml", manifest.as_bytes()),
+            ("classes.dex", &dex),
+            ("resources.arsc", &resources),
+        ])
+    }
+
+    #[test]
+    fn test_parse_splits() {
+        let base = fs::read("tests/apk/signed.apk").unwrap();
+        let split = split_apk("feature", ".FeatureActivity");
+        let apk = parse_splits([Cursor::new(split), Cursor::new(base.clone())]).unwrap();
+        let manifest = apk.manifest.as_ref().unwrap();
+        assert_eq!(manifest.split, None);
+        assert!(manifest.has_permission("android.permission.CAMERA"));
+        assert_eq!(manifest.permissions.len(), 3);
+        assert_eq!(
+            manifest.declared_permissions[0].name,
+            "com.test.permission.feature"
+        );
+        let features: Vec<_> = manifest
+            .features
+            .iter()
+            .map(|f| f.name.as_deref())
+            .collect();
+        assert_eq!(features, [Some("android.hardware.camera")]);
+        assert_eq!(manifest.libraries[0].name, "com.test.feature");
+        assert_eq!(
+            manifest.application.metadata,
+            [Metadata {
+                name: "com.test.feature".into(),
+                value: Some(MetadataValue::Boolean(true)),
+                resource: None,
+            }]
+        );
+        assert_eq!(manifest.queries.packages, ["com.test.feature"]);
+        let activities: Vec<_> = manifest
+            .activities
+            .iter()
+            .map(|a| (a.name.as_str(), a.split.as_deref()))
+            .collect();
+        assert_eq!(
+            activities,
+            [
+                ("com.test.TestActivity", None),
+                ("com.test.dexompiler.FeatureActivity", Some("feature"))
+            ]
+        );
+
+        let method = |class_type: &str| {
+            apk.methods
+                .iter()
+                .find(|m| m.signature.class_type == class_type)
+                .unwrap()
+        };
+        assert_eq!(method("LTestBasic;").split, None);
+        assert_eq!(method("LCallGraph;").split.as_deref(), Some("feature"));
+        assert_eq!(apk.methods.len(), 8);
+        assert!(apk.verification.is_verified());
+        assert!(apk.resources.is_some());
+
+        // The base is told apart without the manifests
+        let split = split_apk("feature", ".FeatureActivity");
+        let apk = ParseOptions::new()
+            .skip_manifest(true)
+            .parse_splits([Cursor::new(split), Cursor::new(base)])
+            .unwrap();
+        assert!(apk.manifest.is_none());
+        assert!(apk.verification.is_verified());
+        assert_eq!(
+            apk.methods
+                .iter()
+                .find(|m| m.signature.class_type == "LCallGraph;")
+                .unwrap()
+                .split
+                .as_deref(),
+            Some("feature")
+        );
+
+        assert!(matches!(
+            parse_splits(Vec::<Cursor<Vec<u8>>>::new()),
+            Err(ApkParseError::NoApk)
+        ));
+    }
+
+    #[test]
+    fn test_parse_bundle() {
+        let base = fs::read("tests/apk/signed.apk").unwrap();
+        let split = split_apk("config.arm64_v8a", ".ConfigActivity");
+        let apks = zip(&[
+            ("toc.pb", b""),
+            ("splits/base-master.apk", &base),
+            ("splits/base-arm64_v8a.apk", &split),
+            ("standalones/standalone-arm64_v8a.apk", &base),
+        ]);
+        let apk = parse_bundle(Cursor::new(apks)).unwrap();
+        assert_eq!(apk.methods.len(), 8);
+        assert_eq!(apk.manifest.unwrap().activities.len(), 2);
+
+        let xapk = zip(&[
+            ("manifest.json", b"{}"),
+            ("com.test.dexompiler.apk", &base),
+            ("config.arm64_v8a.apk", &split),
+            ("icon.png", b"\x89PNG\r\n\x1a\n"),
+        ]);
+        let apk = parse_bundle(Cursor::new(xapk)).unwrap();
+        assert_eq!(apk.methods.len(), 8);
+        assert_eq!(
+            apk.manifest.unwrap().activities[1].split.as_deref(),
+            Some("config.arm64_v8a")
+        );
+    }
+
+    #[test]
+    fn test_parse_aab() {
+        let aab = zip(&[
+            ("BundleConfig.pb", b""),
+            (
+                "base/manifest/AndroidManifest.xml",
+                &fs::read("tests/manifest/d.pb").unwrap(),
+            ),
+            (
+                "base/dex/classes.dex",
+                &fs::read("tests/dex/hello_world.dex").unwrap(),
+            ),
+            ("base/resources.pb", b""),
+            (
+                "feature/manifest/AndroidManifest.xml",
+                &fs::read("tests/manifest/feature.pb").unwrap(),
+            ),
+            (
+                "feature/dex/classes2.dex",
+                &fs::read("tests/dex/call_graph.dex").unwrap(),
+            ),
+        ]);
+        let apk = parse(Cursor::new(aab)).unwrap();
+
+        let manifest = apk.manifest.as_ref().unwrap();
+        assert_eq!(manifest.package.as_deref(), Some("com.example.rich"));
+        assert_eq!(manifest.split, None);
+        let feature = manifest.activities.last().unwrap();
+        assert_eq!(feature.name, "com.example.rich.FeatureActivity");
+        assert_eq!(feature.split.as_deref(), Some("feature"));
+        assert!(manifest.activities[0].split.is_none());
+
+        assert_eq!(apk.methods.len(), 8);
+        let splits: HashSet<_> = apk
+            .methods
+            .iter()
+            .map(|m| (m.signature.class_type.as_str(), m.split.as_deref()))
+            .collect();
+        assert_eq!(
+            splits,
+            HashSet::from([("LTestBasic;", None), ("LCallGraph;", Some("feature"))])
+        );
+    }
+
+    #[test]
+    fn test_parse_dex() {
+        let hello_world = fs::read("tests/dex/hello_world.dex").unwrap();
+        let call_graph = fs::read("tests/dex/call_graph.dex").unwrap();
+
+        let apk = parse_dex(&hello_world).unwrap();
+        assert!(apk.manifest.is_none());
+        assert_eq!(apk.methods.len(), 2);
+        assert_eq!(apk.call_graph.len(), 2);
+
+        let apk = parse_dexes(&[&hello_world, &call_graph]).unwrap();
+        assert_eq!(apk.methods.len(), 8);
+        let apk = parse_dexes(&[fs::read("tests/dex/container.dex").unwrap()]).unwrap();
+        assert_eq!(apk.methods.len(), 8);
+
+        assert!(matches!(
+            parse_dex(b"PK\x03\x04"),
+            Err(ApkParseError::DexReadError(_))
+        ));
+    }
+
+    #[test]
+    fn test_parse_library() {
+        let classes_jar = zip(&[
+            (
+                "classes.dex",
+                &fs::read("tests/dex/hello_world.dex").unwrap(),
+            ),
+            ("META-INF/MANIFEST.MF", b"Manifest-Version: 1.0\r\n"),
+        ]);
+        let bytecode_jar = zip(&[("com/example/Foo.class", b"\xca\xfe\xba\xbe")]);
+        let aar = zip(&[
+            (
+                "AndroidManifest.xml",
+                br#"<manifest package="com.example.sdk"/>"#,
+            ),
+            ("classes.jar", &classes_jar),
+            ("libs/bytecode.jar", &bytecode_jar),
+            ("R.txt", b""),
+        ]);
+        let apk = parse_library(Cursor::new(aar)).unwrap();
+        assert!(apk.manifest.is_none());
+        assert_eq!(apk.methods.len(), 2);
+        assert_eq!(apk.files.len(), 4);
+
+        let jar = zip(&[(
+            "classes.dex",
+            &fs::read("tests/dex/call_graph.dex").unwrap(),
+        )]);
+        let apk = parse_library(Cursor::new(jar)).unwrap();
+        assert_eq!(apk.methods.len(), 6);
+    }
+
+    #[test]
+    fn test_read_xml() {
+        let apk = zip(&[
+            ("res/xml/a.xml", b"<a><b/></a>"),
+            ("res/xml/b.xml", b"<a><b></a>"),
+        ]);
+        let parsed = super::Apk::default();
+        let root = parsed.read_xml(Cursor::new(&apk), "res/xml/a.xml").unwrap();
+        assert_eq!(root.unwrap().children.len(), 1);
+        assert!(matches!(
+            parsed.read_xml(Cursor::new(&apk), "res/xml/b.xml"),
+            Err(ApkParseError::XmlError { path, .. }) if path == "res/xml/b.xml"
+        ));
+    }
+
+    #[test]
+    fn test_parse_options() {
+        let signed = fs::read("tests/apk/signed.apk").unwrap();
+        let apk = ParseOptions::new()
+            .skip_code(true)
+            .parse(Cursor::new(&signed))
+            .unwrap();
+        assert!(apk.manifest.is_some());
+        assert!(apk.methods.is_empty());
+        assert!(apk.verification.is_verified());
+        assert!(apk.files.iter().all(|f| f.sha256.is_some()));
+        let apk = ParseOptions::new()
+            .skip_code(true)
+            .skip_signatures(true)
+            .skip_hashes(true)
+            .parse(Cursor::new(&signed))
+            .unwrap();
+        assert!(apk.manifest.is_some());
+        assert!(apk.methods.is_empty());
+        assert_eq!(apk.signatures, Default::default());
+        assert!(!apk.verification.is_verified());
+        assert!(apk.files.iter().all(|f| f.sha256.is_none()));
+        let apk = ParseOptions::new()
+            .skip_manifest(true)
+            .parse(Cursor::new(&signed))
+            .unwrap();
+        assert!(apk.manifest.is_none());
+        assert_eq!(apk.methods.len(), 2);
+
+        let dexes = [
+            fs::read("tests/dex/hello_world.dex").unwrap(),
+            fs::read("tests/dex/call_graph.dex").unwrap(),
+        ];
+        // The fixtures are in the default package
+        let apk = ParseOptions::new()
+            .include_package("com.example")
+            .parse_dexes(&dexes)
+            .unwrap();
+        assert!(apk.methods.is_empty());
+
+        let apk = ParseOptions::new()
+            .max_methods(3)
+            .max_instructions(2)
+            .opcodes_only(true)
+            .parse_dexes(&dexes)
+            .unwrap();
+        assert_eq!(apk.methods.len(), 3);
+        assert!(apk.call_graph.len() > 3);
+        assert!(apk
+            .methods
+            .iter()
+            .all(|m| m.insns.len() <= 2 && m.insns.iter().all(|i| i.method_id.is_none())));
+
+        let mut sorted = parse_dexes(&dexes).unwrap();
+        sorted.sort_topologically();
+        let apk = ParseOptions::new()
+            .order(MethodOrder::Topological)
+            .parse_dexes(&dexes)
+            .unwrap();
+        let signatures = |apk: &super::Apk| {
+            apk.methods
+                .iter()
+                .map(|m| m.signature.clone())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(signatures(&apk), signatures(&sorted));
+
+        let broken = zip(&[
+            ("AndroidManifest.xml", b"\x03\x00\x08\x00broken"),
+            ("classes.dex", &dexes[0]),
+        ]);
+        assert!(parse(Cursor::new(&broken)).is_err());
+        let apk = ParseOptions::new()
+            .fatal_errors(false)
+            .parse(Cursor::new(&broken))
+            .unwrap();
+        assert!(apk.manifest.is_none());
+        assert_eq!(apk.methods.len(), 2);
+
+        // Truncated header
+        let corrupt = zip(&[
+            ("classes.dex", &dexes[0]),
+            ("classes2.dex", b"dex\n035\0\x01\x02"),
+        ]);
+        assert_eq!(parse(Cursor::new(&corrupt)).unwrap().methods.len(), 2);
+        assert!(matches!(
+            ParseOptions::new()
+                .fatal_errors(true)
+                .parse(Cursor::new(&corrupt)),
+            Err(ApkParseError::DexReadError(_))
+        ));
+    }
 }
@@ -0,0 +1,347 @@
+use std::fmt::Debug;
+
+use crate::{
+    finding::{Finding, Severity},
+    manifest::{
+        component::{Component, ComponentKind},
+        intent_filter::IntentFilter,
+        Manifest,
+    },
+};
+
+/// Issues a rule found, `(location, message)` pairs
+pub type Violations = Vec<(String, String)>;
+
+/// A check over the manifest, identified by its ID (e.g. `debuggable`)
+pub struct LintRule {
+    pub id: String,
+    pub severity: Severity,
+    check: Box<dyn Fn(&Manifest) -> Violations + Send + Sync>,
+}
+
+impl LintRule {
+    pub fn new(
+        id: &str,
+        severity: Severity,
+        check: impl Fn(&Manifest) -> Violations + Send + Sync + 'static,
+    ) -> Self {
+        Self {
+            id: id.to_string(),
+            severity,
+            check: Box::new(check),
+        }
+    }
+}
+
+impl Debug for LintRule {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("LintRule")
+            .field("id", &self.id)
+            .field("severity", &self.severity)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Rule based linter of the manifest, see `Default` for the built-in rules
+#[derive(Debug)]
+pub struct Linter {
+    pub rules: Vec<LintRule>,
+}
+
+impl Linter {
+    /// A linter without any rules
+    pub fn empty() -> Self {
+        Self { rules: Vec::new() }
+    }
+
+    pub fn with_rule(mut self, rule: LintRule) -> Self {
+        self.rules.push(rule);
+        self
+    }
+
+    /// Switches off the rule with the given ID
+    pub fn without_rule(mut self, id: &str) -> Self {
+        self.rules.retain(|r| r.id != id);
+        self
+    }
+
+    /// Runs every rule, findings are grouped by rule in the order the rules were added
+    pub fn lint(&self, manifest: &Manifest) -> Vec<Finding> {
+        self.rules
+            .iter()
+            .flat_map(|rule| {
+                (rule.check)(manifest)
+                    .into_iter()
+                    .map(|(location, message)| Finding {
+                        rule: rule.id.clone(),
+                        severity: rule.severity,
+                        location,
+                        message,
+                    })
+            })
+            .collect()
+    }
+}
+
+fn kinded(
+    kind: ComponentKind,
+    components: &[Component],
+) -> impl Iterator<Item = (ComponentKind, &Component)> {
+    components.iter().map(move |c| (kind, c))
+}
+
+/// Every component with its kind, aliases included
+fn components(manifest: &Manifest) -> impl Iterator<Item = (ComponentKind, &Component)> {
+    kinded(ComponentKind::Activity, &manifest.activities)
+        .chain(kinded(
+            ComponentKind::ActivityAlias,
+            &manifest.activity_aliases,
+        ))
+        .chain(kinded(ComponentKind::Service, &manifest.services))
+        .chain(kinded(ComponentKind::Receiver, &manifest.receivers))
+        .chain(kinded(ComponentKind::Provider, &manifest.providers))
+}
+
+fn location(kind: ComponentKind, component: &Component) -> String {
+    format!("{} {}", kind.tag(), component.name)
+}
+
+fn is_launcher(filter: &IntentFilter) -> bool {
+    filter
+        .actions
+        .iter()
+        .any(|a| a == "android.intent.action.MAIN")
+        && filter
+            .categories
+            .iter()
+            .any(|c| c == "android.intent.category.LAUNCHER")
+}
+
+/// Permissions that together allow exfiltrating sensitive data
+const DANGEROUS_COMBINATIONS: [&[&str]; 3] = [
+    &[
+        "android.permission.RECEIVE_SMS",
+        "android.permission.INTERNET",
+    ],
+    &["android.permission.READ_SMS", "android.permission.INTERNET"],
+    &[
+        "android.permission.RECORD_AUDIO",
+        "android.permission.INTERNET",
+    ],
+];
+
+fn exported_without_permission(manifest: &Manifest) -> Violations {
+    components(manifest)
+        .filter(|(kind, c)| {
+            let kind = match kind {
+                ComponentKind::ActivityAlias => ComponentKind::Activity,
+                kind => *kind,
+            };
+            manifest.is_exported(kind, c)
+                && c.permission.is_none()
+                && (kind != ComponentKind::Provider
+                    || c.read_permission.is_none() || c.write_permission.is_none())
+                // The launcher has to be reachable
+                && !c.intent_filters.iter().any(is_launcher)
+        })
+        .map(|(kind, c)| {
+            (
+                location(kind, c),
+                "Exported component is not protected by a permission".to_string(),
+            )
+        })
+        .collect()
+}
+
+fn debuggable(manifest: &Manifest) -> Violations {
+    match manifest.application.debuggable {
+        Some(true) => vec![(
+            "application".to_string(),
+            "android:debuggable is enabled".to_string(),
+        )],
+        _ => vec![],
+    }
+}
+
+fn test_only(manifest: &Manifest) -> Violations {
+    match manifest.application.test_only {
+        Some(true) => vec![(
+            "application".to_string(),
+            "android:testOnly is enabled".to_string(),
+        )],
+        _ => vec![],
+    }
+}
+
+fn allow_backup(manifest: &Manifest) -> Violations {
+    match manifest.application.allow_backup {
+        Some(false) => vec![],
+        _ => vec![(
+            "application".to_string(),
+            "App data can be backed up, android:allowBackup defaults to true".to_string(),
+        )],
+    }
+}
+
+fn dangerous_permissions(manifest: &Manifest) -> Violations {
+    DANGEROUS_COMBINATIONS
+        .iter()
+        .filter(|permissions| permissions.iter().all(|p| manifest.has_permission(p)))
+        .map(|permissions| {
+            (
+                "manifest".to_string(),
+                format!("Requests {}", permissions.join(" and ")),
+            )
+        })
+        .collect()
+}
+
+fn deep_link_without_auto_verify(manifest: &Manifest) -> Violations {
+    components(manifest)
+        .filter(|(_, c)| {
+            c.intent_filters.iter().any(|f| {
+                !f.auto_verify
+                    && f.categories
+                        .iter()
+                        .any(|c| c == "android.intent.category.BROWSABLE")
+                    && f.data
+                        .iter()
+                        .any(|d| matches!(d.scheme.as_deref(), Some("http" | "https")))
+            })
+        })
+        .map(|(kind, c)| {
+            (
+                location(kind, c),
+                "Browsable web links are not verified (android:autoVerify)".to_string(),
+            )
+        })
+        .collect()
+}
+
+fn provider_broad_grant(manifest: &Manifest) -> Violations {
+    manifest
+        .providers
+        .iter()
+        .filter(|p| p.grant_uri_permissions == Some(true))
+        .map(|p| {
+            (
+                location(ComponentKind::Provider, p),
+                "URI permissions can be granted for any path of the provider".to_string(),
+            )
+        })
+        .collect()
+}
+
+/// Exported components without permission, debuggable and test-only builds, backups,
+/// dangerous permission combinations, unverified deep links and broad provider grants
+impl Default for Linter {
+    fn default() -> Self {
+        Self::empty()
+            .with_rule(LintRule::new(
+                "exported-without-permission",
+                Severity::Medium,
+                exported_without_permission,
+            ))
+            .with_rule(LintRule::new("debuggable", Severity::High, debuggable))
+            .with_rule(LintRule::new("test-only", Severity::Medium, test_only))
+            .with_rule(LintRule::new("allow-backup", Severity::Low, allow_backup))
+            .with_rule(LintRule::new(
+                "dangerous-permissions",
+                Severity::High,
+                dangerous_permissions,
+            ))
+            .with_rule(LintRule::new(
+                "deep-link-without-auto-verify",
+                Severity::Low,
+                deep_link_without_auto_verify,
+            ))
+            .with_rule(LintRule::new(
+                "provider-broad-grant",
+                Severity::Medium,
+                provider_broad_grant,
+            ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs::File, io::Read};
+
+    use super::{LintRule, Linter};
+    use crate::{
+        finding::Severity,
+        manifest::{
+            component::Component,
+            intent_filter::{IntentData, IntentFilter},
+            parse, Manifest,
+        },
+    };
+
+    #[test]
+    fn test_default_rules() {
+        let mut buf = Vec::new();
+        File::open("tests/manifest/d.xml")
+            .unwrap()
+            .read_to_end(&mut buf)
+            .unwrap();
+        let manifest = parse(&buf).unwrap().unwrap();
+        let findings: Vec<_> = Linter::default()
+            .lint(&manifest)
+            .into_iter()
+            .map(|f| (f.rule, f.location))
+            .collect();
+        assert_eq!(
+            findings,
+            vec![
+                (
+                    "exported-without-permission".into(),
+                    "receiver com.example.rich.SmsReceiver".into()
+                ),
+                ("debuggable".into(), "application".into()),
+                ("test-only".into(), "application".into()),
+                ("dangerous-permissions".into(), "manifest".into()),
+                (
+                    "provider-broad-grant".into(),
+                    "provider androidx.core.content.FileProvider".into()
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_custom_rules() {
+        let manifest = Manifest {
+            package: Some("com.example".into()),
+            activities: vec![Component {
+                name: "com.example.Link".into(),
+                exported: Some(true),
+                permission: Some("com.example.LINK".into()),
+                intent_filters: vec![IntentFilter {
+                    categories: vec!["android.intent.category.BROWSABLE".into()],
+                    data: vec![IntentData {
+                        scheme: Some("https".into()),
+                        ..Default::default()
+                    }],
+                    ..Default::default()
+                }],
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+        let linter = Linter::default()
+            .without_rule("allow-backup")
+            .with_rule(LintRule::new("no-package", Severity::Info, |m| {
+                match m.package.as_deref() {
+                    Some("com.example") => vec![("manifest".into(), "Example app".into())],
+                    _ => vec![],
+                }
+            }));
+        let findings = linter.lint(&manifest);
+        assert_eq!(findings.len(), 2);
+        assert_eq!(findings[0].rule, "deep-link-without-auto-verify");
+        assert_eq!(findings[0].severity, Severity::Low);
+        assert_eq!(findings[0].location, "activity com.example.Link");
+        assert_eq!(findings[1].rule, "no-package");
+        assert_eq!(findings[1].message, "Example app");
+    }
+}
@@ -0,0 +1,76 @@
+use serde::Serialize;
+
+use crate::{
+    manifest::{
+        metadata::Metadata,
+        value::{parse_bool, ResourceRef, TextValue},
+    },
+    xml::RawElement,
+};
+
+/// Data of the `<application>` element besides its components
+#[derive(Debug, Default, Serialize, PartialEq, Eq)]
+pub struct Application {
+    /// `android:name`, the `Application` subclass instantiated before any component
+    pub name: Option<String>,
+    /// `android:label`, see `Apk::label` for its resolved value
+    pub label: Option<TextValue>,
+    pub icon: Option<ResourceRef>,
+    pub debuggable: Option<bool>,
+    pub allow_backup: Option<bool>,
+    pub uses_cleartext_traffic: Option<bool>,
+    pub network_security_config: Option<ResourceRef>,
+    pub test_only: Option<bool>,
+    pub extract_native_libs: Option<bool>,
+    pub metadata: Vec<Metadata>,
+}
+
+/// `<uses-sdk>` API levels
+#[derive(Debug, Default, Serialize, PartialEq, Eq)]
+pub struct UsesSdk {
+    pub min: Option<i32>,
+    pub target: Option<i32>,
+    pub max: Option<i32>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+pub enum InstallLocation {
+    Auto,
+    InternalOnly,
+    PreferExternal,
+}
+
+impl InstallLocation {
+    /// Parses the enum value of a binary manifest or the name of a text one
+    pub(crate) fn parse(value: &str) -> Option<Self> {
+        match value {
+            "auto" | "0" => Some(InstallLocation::Auto),
+            "internalOnly" | "1" => Some(InstallLocation::InternalOnly),
+            "preferExternal" | "2" => Some(InstallLocation::PreferExternal),
+            _ => None,
+        }
+    }
+}
+
+/// An `<instrumentation>` element, a test runner or monitor of another package
+#[derive(Debug, Clone, Serialize, PartialEq, Eq)]
+pub struct Instrumentation {
+    /// `Instrumentation` subclass
+    pub name: String,
+    /// Package the instrumentation runs against
+    pub target_package: Option<String>,
+    pub functional_test: Option<bool>,
+    pub handle_profiling: Option<bool>,
+}
+
+impl Instrumentation {
+    pub(crate) fn parse(mut value: RawElement) -> Option<Self> {
+        let mut take = |name: &str| value.attributes.remove(&format!("android:{name}"));
+        Some(Instrumentation {
+            name: take("name")?,
+            target_package: take("targetPackage"),
+            functional_test: take("functionalTest").and_then(|v| parse_bool(&v)),
+            handle_profiling: take("handleProfiling").and_then(|v| parse_bool(&v)),
+        })
+    }
+}
@@ -1,32 +1,204 @@
-use axmldecoder::{Element, Node};
 use serde::Serialize;
 
-use crate::manifest::{intent_filter::IntentFilter, metadata::Metadata};
+use crate::{
+    manifest::{
+        intent_filter::IntentFilter,
+        metadata::Metadata,
+        value::{parse_bool, parse_int},
+    },
+    xml::RawElement,
+};
 
 #[derive(Debug, Default, Serialize, PartialEq, Eq)]
 pub struct Component {
+    /// Fully qualified class name (e.g. `com.example.Foo`)
     pub name: String,
+    /// `android:name` as declared, possibly relative to the package (e.g. `.Foo` or `Foo`)
+    pub declared_name: String,
+    /// Class descriptor of the component (e.g. `Lcom/example/Foo;`)
+    pub descriptor: String,
     pub intent_filters: Vec<IntentFilter>,
-    pub metadata: Option<Metadata>,
+    pub metadata: Vec<Metadata>,
     pub permission: Option<String>,
+    /// `android:exported` as declared, see `Component::is_exported` for the effective value
+    pub exported: Option<bool>,
+    pub enabled: Option<bool>,
+    pub process: Option<String>,
+    pub direct_boot_aware: Option<bool>,
+    pub task_affinity: Option<String>,
+    pub launch_mode: Option<LaunchMode>,
+    /// Names of the `android:foregroundServiceType` flags (e.g. `dataSync`, `location`)
+    pub foreground_service_types: Vec<String>,
+    /// `android:authorities` of a provider
+    pub authorities: Vec<String>,
+    pub grant_uri_permissions: Option<bool>,
+    pub read_permission: Option<String>,
+    pub write_permission: Option<String>,
+    /// `android:targetActivity` of an activity alias, see `Manifest::alias_target`
+    pub target_activity: Option<String>,
+    /// Split APK declaring the component, `None` for the base APK
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub split: Option<String>,
 }
 
-impl From<Element> for Component {
-    fn from(mut value: Element) -> Self {
-        let mut component = Component::default();
-        component.name = value.attributes.remove("android:name").unwrap_or_default();
+/// Kind of a component, the element it is declared with
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+pub enum ComponentKind {
+    Activity,
+    /// `<activity-alias>`, another name (and other filters) for an activity
+    ActivityAlias,
+    Service,
+    Receiver,
+    Provider,
+}
+
+impl ComponentKind {
+    /// Name of the element declaring the component
+    pub fn tag(&self) -> &'static str {
+        match self {
+            ComponentKind::Activity => "activity",
+            ComponentKind::ActivityAlias => "activity-alias",
+            ComponentKind::Service => "service",
+            ComponentKind::Receiver => "receiver",
+            ComponentKind::Provider => "provider",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+pub enum LaunchMode {
+    Standard,
+    SingleTop,
+    SingleTask,
+    SingleInstance,
+    SingleInstancePerTask,
+}
+
+impl LaunchMode {
+    /// Parses the enum value of a binary manifest or the name of a text one
+    fn parse(value: &str) -> Option<Self> {
+        match value {
+            "standard" => Some(LaunchMode::Standard),
+            "singleTop" => Some(LaunchMode::SingleTop),
+            "singleTask" => Some(LaunchMode::SingleTask),
+            "singleInstance" => Some(LaunchMode::SingleInstance),
+            "singleInstancePerTask" => Some(LaunchMode::SingleInstancePerTask),
+            other => match parse_int(other)? {
+                0 => Some(LaunchMode::Standard),
+                1 => Some(LaunchMode::SingleTop),
+                2 => Some(LaunchMode::SingleTask),
+                3 => Some(LaunchMode::SingleInstance),
+                4 => Some(LaunchMode::SingleInstancePerTask),
+                _ => None,
+            },
+        }
+    }
+}
+
+/// Flags of `android:foregroundServiceType`
+const FOREGROUND_SERVICE_TYPES: [(u32, &str); 14] = [
+    (0x1, "dataSync"),
+    (0x2, "mediaPlayback"),
+    (0x4, "phoneCall"),
+    (0x8, "location"),
+    (0x10, "connectedDevice"),
+    (0x20, "mediaProjection"),
+    (0x40, "camera"),
+    (0x80, "microphone"),
+    (0x100, "health"),
+    (0x200, "remoteMessaging"),
+    (0x400, "systemExempted"),
+    (0x800, "shortService"),
+    (0x2000, "mediaProcessing"),
+    (0x40000000, "specialUse"),
+];
+
+fn parse_foreground_service_types(value: &str) -> Vec<String> {
+    match parse_int(value) {
+        Some(flags) => FOREGROUND_SERVICE_TYPES
+            .iter()
+            .filter(|(flag, _)| flags as u32 & flag != 0)
+            .map(|(_, name)| name.to_string())
+            .collect(),
+        None => value.split('|').map(|s| s.trim().to_string()).collect(),
+    }
+}
+
+/// Fully qualified name of a class declared in the manifest, names starting with a `.`
+/// or without any `.` are relative to the package
+pub(crate) fn canonical_name(package: Option<&str>, name: &str) -> String {
+    match package {
+        Some(package) if name.starts_with('.') => format!("{package}{name}"),
+        Some(package) if !name.contains('.') => format!("{package}.{name}"),
+        _ => name.to_string(),
+    }
+}
+
+/// Class descriptor of a fully qualified class name
+pub(crate) fn descriptor(name: &str) -> String {
+    format!("L{};", name.replace('.', "/"))
+}
+
+impl Component {
+    /// Resolves the declared names against the package of the manifest
+    pub(crate) fn resolve(&mut self, package: Option<&str>) {
+        self.name = canonical_name(package, &self.declared_name);
+        self.descriptor = descriptor(&self.name);
+        if let Some(target) = self.target_activity.as_mut() {
+            *target = canonical_name(package, target);
+        }
+    }
+
+    /// Whether other apps can start the component, `android:exported` if declared, otherwise:
+    /// * providers are exported when targeting API 16 or lower
+    /// * other components are exported when they have intent filters
+    ///   (targeting API 31 or higher requires an explicit value in this case)
+    ///
+    /// ### Arguments
+    /// * `kind`: The kind of the component.
+    /// * `target_sdk`: `android:targetSdkVersion` of the app, it defaults to 1 when missing.
+    pub fn is_exported(&self, kind: ComponentKind, target_sdk: Option<i32>) -> bool {
+        self.exported.unwrap_or(match kind {
+            ComponentKind::Provider => target_sdk.unwrap_or(1) <= 16,
+            _ => !self.intent_filters.is_empty(),
+        })
+    }
+}
+
+impl From<RawElement> for Component {
+    fn from(mut value: RawElement) -> Self {
+        let mut take = |name: &str| value.attributes.remove(&format!("android:{name}"));
+        let mut component = Component {
+            declared_name: take("name").unwrap_or_default(),
+            permission: take("permission"),
+            exported: take("exported").and_then(|v| parse_bool(&v)),
+            enabled: take("enabled").and_then(|v| parse_bool(&v)),
+            process: take("process"),
+            direct_boot_aware: take("directBootAware").and_then(|v| parse_bool(&v)),
+            task_affinity: take("taskAffinity"),
+            launch_mode: take("launchMode").and_then(|v| LaunchMode::parse(&v)),
+            foreground_service_types: take("foregroundServiceType")
+                .map(|v| parse_foreground_service_types(&v))
+                .unwrap_or_default(),
+            authorities: take("authorities")
+                .map(|v| v.split(';').map(|s| s.trim().to_string()).collect())
+                .unwrap_or_default(),
+            grant_uri_permissions: take("grantUriPermissions").and_then(|v| parse_bool(&v)),
+            read_permission: take("readPermission"),
+            write_permission: take("writePermission"),
+            target_activity: take("targetActivity"),
+            ..Default::default()
+        };
 
-        for child in value.children {
-            if let Node::Element(element) = child {
-                match element.get_tag() {
-                    "intent-filter" => component.intent_filters.push(element.into()),
-                    "meta-data" => component.metadata = Some(element.into()),
-                    _ => {}
-                }
+        for element in value.children {
+            match element.tag.as_str() {
+                "intent-filter" => component.intent_filters.push(element.into()),
+                "meta-data" => component.metadata.push(element.into()),
+                _ => {}
             }
         }
 
-        component.permission = value.attributes.remove("android:permission");
+        component.resolve(None);
         component
     }
 }
@@ -0,0 +1,51 @@
+use serde::Serialize;
+
+use crate::{
+    manifest::value::{parse_bool, parse_int},
+    xml::RawElement,
+};
+
+/// A `<uses-feature>` element, hardware or software the app relies on
+#[derive(Debug, Clone, Serialize, PartialEq, Eq)]
+pub struct UsesFeature {
+    /// Feature name (e.g. `android.hardware.camera`), missing for OpenGL ES requirements
+    pub name: Option<String>,
+    /// `android:glEsVersion`, major version in the upper 16 bits (e.g. `0x20000` for 2.0)
+    pub gl_es_version: Option<i32>,
+    /// `android:required`, defaults to `true`
+    pub required: bool,
+}
+
+/// A `<uses-library>` element, shared library the app is linked against
+#[derive(Debug, Clone, Serialize, PartialEq, Eq)]
+pub struct UsesLibrary {
+    pub name: String,
+    /// `android:required`, defaults to `true`
+    pub required: bool,
+}
+
+impl From<RawElement> for UsesFeature {
+    fn from(mut value: RawElement) -> Self {
+        let mut take = |name: &str| value.attributes.remove(&format!("android:{name}"));
+        UsesFeature {
+            name: take("name"),
+            gl_es_version: take("glEsVersion").and_then(|v| parse_int(&v)),
+            required: take("required")
+                .and_then(|v| parse_bool(&v))
+                .unwrap_or(true),
+        }
+    }
+}
+
+impl UsesLibrary {
+    pub(crate) fn parse(mut value: RawElement) -> Option<Self> {
+        Some(UsesLibrary {
+            name: value.attributes.remove("android:name")?,
+            required: value
+                .attributes
+                .remove("android:required")
+                .and_then(|v| parse_bool(&v))
+                .unwrap_or(true),
+        })
+    }
+}
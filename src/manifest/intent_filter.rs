@@ -1,22 +1,85 @@
-use axmldecoder::Element;
 use serde::Serialize;
 
+use crate::{
+    manifest::value::{parse_bool, parse_int},
+    xml::RawElement,
+};
+
 #[derive(Debug, Default, Serialize, PartialEq, Eq)]
 pub struct IntentFilter {
-    pub action: String,
+    pub actions: Vec<String>,
+    pub categories: Vec<String>,
+    pub data: Vec<IntentData>,
+    /// `android:priority`, higher values are matched first
+    pub priority: Option<i32>,
+    /// `android:autoVerify`, asks the system to verify the app links of the filter
+    pub auto_verify: bool,
+    /// `android:order`, ordering of filters with the same priority
+    pub order: Option<i32>,
 }
 
-impl From<Element> for IntentFilter {
-    fn from(value: Element) -> Self {
-        for child in value.children {
-            if let axmldecoder::Node::Element(mut element) = child {
-                if element.get_tag() == "action" {
-                    if let Some(action) = element.attributes.remove("android:name") {
-                        return IntentFilter { action };
-                    }
-                }
+/// A `<data>` element, each attribute is matched independently of the element it is declared in
+#[derive(Debug, Default, Serialize, PartialEq, Eq)]
+pub struct IntentData {
+    pub scheme: Option<String>,
+    pub host: Option<String>,
+    pub port: Option<String>,
+    pub path: Option<String>,
+    pub path_prefix: Option<String>,
+    pub path_pattern: Option<String>,
+    pub path_advanced_pattern: Option<String>,
+    pub path_suffix: Option<String>,
+    pub mime_type: Option<String>,
+}
+
+impl From<RawElement> for IntentFilter {
+    fn from(mut value: RawElement) -> Self {
+        let mut filter = IntentFilter {
+            priority: value
+                .attributes
+                .remove("android:priority")
+                .and_then(|p| parse_int(&p)),
+            auto_verify: value
+                .attributes
+                .remove("android:autoVerify")
+                .and_then(|v| parse_bool(&v))
+                .unwrap_or_default(),
+            order: value
+                .attributes
+                .remove("android:order")
+                .and_then(|o| parse_int(&o)),
+            ..Default::default()
+        };
+
+        for mut element in value.children {
+            match element.tag.as_str() {
+                "action" => filter
+                    .actions
+                    .extend(element.attributes.remove("android:name")),
+                "category" => filter
+                    .categories
+                    .extend(element.attributes.remove("android:name")),
+                "data" => filter.data.push(element.into()),
+                _ => {}
             }
         }
-        IntentFilter::default()
+        filter
+    }
+}
+
+impl From<RawElement> for IntentData {
+    fn from(mut value: RawElement) -> Self {
+        let mut take = |name: &str| value.attributes.remove(&format!("android:{name}"));
+        IntentData {
+            scheme: take("scheme"),
+            host: take("host"),
+            port: take("port"),
+            path: take("path"),
+            path_prefix: take("pathPrefix"),
+            path_pattern: take("pathPattern"),
+            path_advanced_pattern: take("pathAdvancedPattern"),
+            path_suffix: take("pathSuffix"),
+            mime_type: take("mimeType"),
+        }
     }
 }
@@ -1,15 +1,104 @@
-use axmldecoder::Element;
 use serde::Serialize;
 
-#[derive(Debug, Default, Serialize, PartialEq, Eq)]
+use crate::{
+    manifest::value::{parse_bool, parse_int, parse_reference, ResourceRef},
+    xml::{RawElement, ValueType},
+};
+
+#[derive(Debug, Default, Clone, Serialize, PartialEq, Eq)]
 pub struct Metadata {
     pub name: String,
+    /// `android:value`
+    pub value: Option<MetadataValue>,
+    /// `android:resource`
+    pub resource: Option<ResourceRef>,
+}
+
+/// Value of a `<meta-data>`, typed as the binary manifest stores it
+#[derive(Debug, Clone, Serialize, PartialEq, Eq)]
+pub enum MetadataValue {
+    String(String),
+    Integer(i32),
+    Boolean(bool),
+    Reference(ResourceRef),
 }
 
-impl From<Element> for Metadata {
-    fn from(mut value: Element) -> Self {
+impl MetadataValue {
+    /// Value from its rendering and compiled type, a string when the type is unknown
+    pub(crate) fn new(value: String, value_type: Option<ValueType>) -> Self {
+        let typed = match value_type {
+            Some(ValueType::Integer) => parse_int(&value).map(MetadataValue::Integer),
+            Some(ValueType::Boolean) => parse_bool(&value).map(MetadataValue::Boolean),
+            Some(ValueType::Reference) => parse_reference(&value).map(MetadataValue::Reference),
+            _ => None,
+        };
+        typed.unwrap_or(MetadataValue::String(value))
+    }
+}
+
+impl From<RawElement> for Metadata {
+    fn from(mut value: RawElement) -> Self {
         Metadata {
             name: value.attributes.remove("android:name").unwrap_or_default(),
+            value: value
+                .attributes
+                .remove("android:value")
+                .map(|v| MetadataValue::new(v, value.types.get("android:value").copied())),
+            resource: value
+                .attributes
+                .remove("android:resource")
+                .and_then(|r| parse_reference(&r)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::{Metadata, MetadataValue};
+    use crate::{
+        manifest::value::ResourceRef,
+        xml::{RawElement, ValueType},
+    };
+
+    fn value(value: &str, value_type: Option<ValueType>) -> Option<MetadataValue> {
+        let element = RawElement {
+            tag: "meta-data".into(),
+            attributes: HashMap::from([("android:value".into(), value.into())]),
+            types: value_type
+                .map(|t| HashMap::from([("android:value".into(), t)]))
+                .unwrap_or_default(),
+            ..Default::default()
+        };
+        Metadata::from(element).value
+    }
+
+    #[test]
+    fn test_value() {
+        // Strings which look like other types
+        for s in ["007", "123", "true", "@string/name"] {
+            assert_eq!(
+                value(s, Some(ValueType::String)),
+                Some(MetadataValue::String(s.into()))
+            );
+            assert_eq!(value(s, None), Some(MetadataValue::String(s.into())));
         }
+        assert_eq!(
+            value("123", Some(ValueType::Integer)),
+            Some(MetadataValue::Integer(123))
+        );
+        assert_eq!(
+            value("0xffffffff", Some(ValueType::Boolean)),
+            Some(MetadataValue::Boolean(true))
+        );
+        assert_eq!(
+            value("@0x7f090008", Some(ValueType::Reference)),
+            Some(MetadataValue::Reference(ResourceRef::Id(0x7f090008)))
+        );
+        assert_eq!(
+            value("1.5", Some(ValueType::Other)),
+            Some(MetadataValue::String("1.5".into()))
+        );
     }
 }
@@ -1,24 +1,77 @@
-mod component;
-mod intent_filter;
-mod metadata;
+pub(crate) mod application;
+pub(crate) mod component;
+pub(crate) mod feature;
+pub(crate) mod intent_filter;
+pub(crate) mod metadata;
+pub(crate) mod permission;
+pub(crate) mod queries;
+pub(crate) mod value;
 
-use axmldecoder::{Node, ParseError, XmlDocument};
 use serde::Serialize;
-use std::collections::HashSet;
 
-use crate::manifest::component::Component;
+use crate::{
+    manifest::{
+        application::{Application, InstallLocation, Instrumentation, UsesSdk},
+        component::{Component, ComponentKind},
+        feature::{UsesFeature, UsesLibrary},
+        permission::{DeclarationKind, PermissionDeclaration, UsesPermission},
+        queries::Queries,
+        value::{parse_bool, parse_int, parse_reference, TextValue},
+    },
+    xml::{self, errors::XmlError, RawElement},
+};
 
-#[derive(Debug, Default, Serialize)]
+#[derive(Debug, Default, Serialize, PartialEq, Eq)]
 pub struct Manifest {
     #[serde(rename = "pkg")]
     pub package: Option<String>,
 
+    #[serde(rename = "vc")]
+    pub version_code: Option<i32>,
+
+    #[serde(rename = "vn")]
+    pub version_name: Option<String>,
+
+    /// Name of the split APK (e.g. `config.arm64_v8a`), `None` for the base APK
+    #[serde(rename = "spl")]
+    pub split: Option<String>,
+
+    #[serde(rename = "sdk")]
+    pub sdk: UsesSdk,
+
+    #[serde(rename = "uid")]
+    pub shared_user_id: Option<String>,
+
+    #[serde(rename = "loc")]
+    pub install_location: Option<InstallLocation>,
+
+    /// Requested permissions, platform and custom ones alike
     #[serde(rename = "prm")]
-    pub permissions: HashSet<String>,
+    pub permissions: Vec<UsesPermission>,
+
+    /// Permissions, permission groups and permission trees defined by the app
+    #[serde(rename = "dpr")]
+    pub declared_permissions: Vec<PermissionDeclaration>,
+
+    #[serde(rename = "ftr")]
+    pub features: Vec<UsesFeature>,
+
+    /// `<uses-library>` elements of the application
+    #[serde(rename = "lib")]
+    pub libraries: Vec<UsesLibrary>,
+
+    #[serde(rename = "qry")]
+    pub queries: Queries,
+
+    #[serde(rename = "ins")]
+    pub instrumentations: Vec<Instrumentation>,
 
     #[serde(rename = "act")]
     pub activities: Vec<Component>,
 
+    #[serde(rename = "als")]
+    pub activity_aliases: Vec<Component>,
+
     #[serde(rename = "svc")]
     pub services: Vec<Component>,
 
@@ -27,80 +80,279 @@ pub struct Manifest {
 
     #[serde(rename = "prv")]
     pub providers: Vec<Component>,
+
+    #[serde(rename = "app")]
+    pub application: Application,
+}
+
+impl Manifest {
+    /// Whether the app requests a permission, by its fully qualified name
+    pub fn has_permission(&self, name: &str) -> bool {
+        self.permissions.iter().any(|p| p.name == name)
+    }
+
+    /// Whether other apps can start the component, see `Component::is_exported`.
+    /// The target API level defaults to the minimum one as on Android.
+    pub fn is_exported(&self, kind: ComponentKind, component: &Component) -> bool {
+        component.is_exported(kind, self.sdk.target.or(self.sdk.min))
+    }
+
+    /// All components, activities first, then services, receivers and providers
+    pub fn components(&self) -> impl Iterator<Item = &Component> {
+        self.activities
+            .iter()
+            .chain(self.services.iter())
+            .chain(self.receivers.iter())
+            .chain(self.providers.iter())
+    }
+
+    /// Activity an alias points to, `None` if it is not declared in the manifest
+    pub fn alias_target(&self, alias: &Component) -> Option<&Component> {
+        let target = alias.target_activity.as_deref()?;
+        self.activities.iter().find(|a| a.name == target)
+    }
+
+    /// Adds the components, permissions, features, libraries, metadata and queries of the
+    /// manifest of a split APK, the ones already declared are kept. The components keep the name
+    /// of the split in `Component::split`.
+    pub(crate) fn merge(&mut self, split: Manifest) {
+        for (components, added) in [
+            (&mut self.activities, split.activities),
+            (&mut self.activity_aliases, split.activity_aliases),
+            (&mut self.services, split.services),
+            (&mut self.receivers, split.receivers),
+            (&mut self.providers, split.providers),
+        ] {
+            for mut component in added {
+                // Manifests of install-time feature modules are also merged into the base one
+                if components.iter().all(|c| c.name != component.name) {
+                    component.split = split.split.clone();
+                    components.push(component);
+                }
+            }
+        }
+        for permission in split.permissions {
+            if !self.has_permission(&permission.name) {
+                self.permissions.push(permission);
+            }
+        }
+        for declaration in split.declared_permissions {
+            if self
+                .declared_permissions
+                .iter()
+                .all(|d| d.name != declaration.name || d.kind != declaration.kind)
+            {
+                self.declared_permissions.push(declaration);
+            }
+        }
+        for feature in split.features {
+            // OpenGL ES requirements have no name
+            if self
+                .features
+                .iter()
+                .all(|f| f.name != feature.name || f.gl_es_version != feature.gl_es_version)
+            {
+                self.features.push(feature);
+            }
+        }
+        for library in split.libraries {
+            if self.libraries.iter().all(|l| l.name != library.name) {
+                self.libraries.push(library);
+            }
+        }
+        for metadata in split.application.metadata {
+            if self
+                .application
+                .metadata
+                .iter()
+                .all(|m| m.name != metadata.name)
+            {
+                self.application.metadata.push(metadata);
+            }
+        }
+        let queries = &mut self.queries;
+        for package in split.queries.packages {
+            if !queries.packages.contains(&package) {
+                queries.packages.push(package);
+            }
+        }
+        for intent in split.queries.intents {
+            if !queries.intents.contains(&intent) {
+                queries.intents.push(intent);
+            }
+        }
+        for provider in split.queries.providers {
+            if !queries.providers.contains(&provider) {
+                queries.providers.push(provider);
+            }
+        }
+    }
+
+    /// Class descriptor of a component name (e.g. `Lcom/example/Foo;`),
+    /// resolving names relative to the package (e.g. `.Foo`).
+    pub fn class_descriptor(&self, name: &str) -> String {
+        component::descriptor(&component::canonical_name(self.package.as_deref(), name))
+    }
 }
 
 macro_rules! push_component {
     ($element:expr, $manifest:expr, $where:expr) => {{
         let mut component = Component::from($element);
-        if let Some(package) = $manifest.package.as_ref() {
-            if let Some(name) = component.name.strip_prefix(package.as_str()) {
-                component.name = name.into();
-            }
-        }
+        component.resolve($manifest.package.as_deref());
         $where.push(component);
     }};
 }
 
-pub fn parse(buf: &[u8]) -> Result<Option<Manifest>, ParseError> {
-    let XmlDocument { root } = axmldecoder::parse(buf)?;
-    match root {
-        Some(Node::Element(root)) => {
-            let package = root.attributes.get("package").map(|s| s.into());
-            let mut manifest = Manifest {
-                package,
-                ..Default::default()
-            };
-            for node in root.children {
-                if let Node::Element(mut element) = node {
-                    match element.get_tag() {
-                        "uses-permission" => {
-                            if let Some(name) = element.attributes.remove("android:name") {
-                                if let Some(perm) = name.strip_prefix("android.permission.") {
-                                    manifest.permissions.insert(perm.into());
-                                }
+/// Parses a binary (compiled) or plain-text `AndroidManifest.xml`
+pub fn parse(buf: &[u8]) -> Result<Option<Manifest>, XmlError> {
+    Ok(xml::parse_raw(buf)?.map(Manifest::from))
+}
+
+/// Parses the protobuf `AndroidManifest.xml` of a module of an Android App Bundle
+pub fn parse_proto(buf: &[u8]) -> Result<Option<Manifest>, XmlError> {
+    Ok(xml::parse_proto(buf)?.map(Manifest::from))
+}
+
+impl From<RawElement> for Manifest {
+    fn from(mut root: RawElement) -> Self {
+        let mut take = |name: &str| root.attributes.remove(name);
+        let mut manifest = Manifest {
+            package: take("package"),
+            version_code: take("android:versionCode").and_then(|v| parse_int(&v)),
+            version_name: take("android:versionName"),
+            split: take("split"),
+            shared_user_id: take("android:sharedUserId"),
+            install_location: take("android:installLocation")
+                .and_then(|v| InstallLocation::parse(&v)),
+            ..Default::default()
+        };
+        for mut element in root.children {
+            match element.tag.as_str() {
+                "uses-permission" => manifest
+                    .permissions
+                    .extend(UsesPermission::parse(element, false)),
+                "uses-permission-sdk-23" | "uses-permission-sdk-m" => manifest
+                    .permissions
+                    .extend(UsesPermission::parse(element, true)),
+                "permission" => manifest
+                    .declared_permissions
+                    .extend(PermissionDeclaration::parse(
+                        element,
+                        DeclarationKind::Permission,
+                    )),
+                "permission-group" => {
+                    manifest
+                        .declared_permissions
+                        .extend(PermissionDeclaration::parse(
+                            element,
+                            DeclarationKind::Group,
+                        ))
+                }
+                "permission-tree" => manifest
+                    .declared_permissions
+                    .extend(PermissionDeclaration::parse(element, DeclarationKind::Tree)),
+                "uses-feature" => manifest.features.push(element.into()),
+                "queries" => manifest.queries.extend(element),
+                "instrumentation" => manifest
+                    .instrumentations
+                    .extend(Instrumentation::parse(element)),
+                "uses-sdk" => {
+                    let mut level = |name: &str| {
+                        element
+                            .attributes
+                            .remove(&format!("android:{name}SdkVersion"))
+                            .and_then(|v| parse_int(&v))
+                    };
+                    manifest.sdk = UsesSdk {
+                        min: level("min"),
+                        target: level("target"),
+                        max: level("max"),
+                    };
+                }
+                "application" => {
+                    let mut take =
+                        |name: &str| element.attributes.remove(&format!("android:{name}"));
+                    manifest.application = Application {
+                        name: take("name"),
+                        label: take("label").map(TextValue::from),
+                        icon: take("icon").and_then(|v| parse_reference(&v)),
+                        debuggable: take("debuggable").and_then(|v| parse_bool(&v)),
+                        allow_backup: take("allowBackup").and_then(|v| parse_bool(&v)),
+                        uses_cleartext_traffic: take("usesCleartextTraffic")
+                            .and_then(|v| parse_bool(&v)),
+                        network_security_config: take("networkSecurityConfig")
+                            .and_then(|v| parse_reference(&v)),
+                        test_only: take("testOnly").and_then(|v| parse_bool(&v)),
+                        extract_native_libs: take("extractNativeLibs").and_then(|v| parse_bool(&v)),
+                        metadata: Vec::new(),
+                    };
+                    for element in element.children {
+                        match element.tag.as_str() {
+                            "activity" => {
+                                push_component!(element, &manifest, manifest.activities)
                             }
-                        }
-                        "application" => {
-                            for node in element.children {
-                                if let Node::Element(element) = node {
-                                    match element.get_tag() {
-                                        "activity" => {
-                                            push_component!(element, &manifest, manifest.activities)
-                                        }
-                                        "service" => {
-                                            push_component!(element, &manifest, manifest.services)
-                                        }
-                                        "receiver" => {
-                                            push_component!(element, &manifest, manifest.receivers)
-                                        }
-                                        "provider" => {
-                                            push_component!(element, &manifest, manifest.providers)
-                                        }
-                                        _ => (),
-                                    }
-                                }
+                            "activity-alias" => {
+                                push_component!(element, &manifest, manifest.activity_aliases)
                             }
+                            "service" => {
+                                push_component!(element, &manifest, manifest.services)
+                            }
+                            "receiver" => {
+                                push_component!(element, &manifest, manifest.receivers)
+                            }
+                            "provider" => {
+                                push_component!(element, &manifest, manifest.providers)
+                            }
+                            "meta-data" => manifest.application.metadata.push(element.into()),
+                            "uses-library" => {
+                                manifest.libraries.extend(UsesLibrary::parse(element))
+                            }
+                            _ => (),
                         }
-                        _ => (),
                     }
                 }
+                _ => (),
             }
-            Ok(Some(manifest))
         }
-        Some(other) => {
-            log::warn!("Unexpected root node: {other:?}");
-            Ok(None)
-        }
-        None => Ok(None),
+        manifest
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::manifest::{component::Component, intent_filter::IntentFilter, metadata::Metadata};
+    use crate::manifest::{
+        application::{Application, InstallLocation, Instrumentation, UsesSdk},
+        component::{Component, ComponentKind, LaunchMode},
+        feature::{UsesFeature, UsesLibrary},
+        intent_filter::{IntentData, IntentFilter},
+        metadata::{Metadata, MetadataValue},
+        permission::{
+            DeclarationKind, PermissionDeclaration, ProtectionBase, ProtectionLevel, UsesPermission,
+        },
+        queries::Queries,
+        value::{ResourceRef, TextValue},
+        Manifest,
+    };
+
+    use super::{parse, parse_proto};
+    use std::{collections::HashSet, fs::File, io::Read};
+
+    fn component(name: &str) -> Component {
+        Component {
+            name: name.into(),
+            declared_name: name.into(),
+            descriptor: format!("L{};", name.replace('.', "/")),
+            ..Default::default()
+        }
+    }
 
-    use super::parse;
-    use std::{fs::File, io::Read};
+    fn permission_names(manifest: &Manifest) -> HashSet<&str> {
+        manifest
+            .permissions
+            .iter()
+            .map(|p| p.name.as_str())
+            .collect()
+    }
 
     #[test]
     fn test_parse_a() {
@@ -111,48 +363,62 @@ mod tests {
             .unwrap();
         let manifest = parse(&buf).unwrap().unwrap();
         assert_eq!(manifest.package, Some("com.test.dexompiler".into()));
+        assert_eq!(manifest.version_code, Some(1017050));
+        assert_eq!(manifest.version_name, Some("1.17.0".into()));
+        assert_eq!(
+            manifest.sdk,
+            UsesSdk {
+                min: Some(23),
+                target: Some(28),
+                max: None
+            }
+        );
+        assert_eq!(manifest.application, Application::default());
         assert_eq!(
-            manifest.permissions,
-            vec!["INTERNET".into(), "FOREGROUND_SERVICE".into()]
-                .into_iter()
-                .collect()
+            permission_names(&manifest),
+            HashSet::from([
+                "android.permission.INTERNET",
+                "android.permission.FOREGROUND_SERVICE"
+            ])
         );
         assert_eq!(
             manifest.activities,
             vec![Component {
-                name: "com.test.TestActivity".into(),
                 permission: None,
                 intent_filters: vec![],
-                metadata: None,
+                metadata: vec![],
+                ..component("com.test.TestActivity")
             }]
         );
         assert_eq!(
             manifest.services,
             vec![Component {
-                name: "com.test.TestService".into(),
                 permission: None,
                 intent_filters: vec![],
-                metadata: None,
+                metadata: vec![],
+                ..component("com.test.TestService")
             }]
         );
         assert_eq!(
             manifest.receivers,
             vec![Component {
-                name: "com.test.TestReceiver".into(),
                 permission: None,
                 intent_filters: vec![],
-                metadata: None,
+                metadata: vec![],
+                ..component("com.test.TestReceiver")
             }]
         );
         assert_eq!(
             manifest.providers,
             vec![Component {
-                name: "com.test.TestProvider".into(),
                 permission: None,
                 intent_filters: vec![],
-                metadata: Some(Metadata {
-                    name: "android.support.FILE_PROVIDER_PATHS".into()
-                }),
+                metadata: vec![Metadata {
+                    name: "android.support.FILE_PROVIDER_PATHS".into(),
+                    value: None,
+                    resource: Some(ResourceRef::Id(0x7f140000)),
+                }],
+                ..component("com.test.TestProvider")
             }]
         );
     }
@@ -166,15 +432,25 @@ mod tests {
             .unwrap();
         let manifest = parse(&buf).unwrap().unwrap();
         assert_eq!(manifest.package, Some("com.hzmjdbvq.segiyntr".into()));
+        assert_eq!(manifest.version_code, Some(1));
+        assert_eq!(manifest.version_name, Some("2.0.0".into()));
+        assert_eq!(manifest.sdk.target, Some(29));
         assert_eq!(
-            manifest.permissions,
-            vec![
-                "ACCESS_NETWORK_STATE".into(),
-                "SYSTEM_ALERT_WINDOW".into(),
-                "INTERNET".into()
-            ]
-            .into_iter()
-            .collect()
+            manifest.application,
+            Application {
+                label: Some(TextValue::Reference(ResourceRef::Id(0x7f040001))),
+                icon: Some(ResourceRef::Id(0x7f010001)),
+                uses_cleartext_traffic: Some(true),
+                ..Default::default()
+            }
+        );
+        assert_eq!(
+            permission_names(&manifest),
+            HashSet::from([
+                "android.permission.ACCESS_NETWORK_STATE",
+                "android.permission.SYSTEM_ALERT_WINDOW",
+                "android.permission.INTERNET"
+            ])
         );
         assert_eq!(
             manifest.activities,
@@ -185,16 +461,19 @@ mod tests {
             ]
             .into_iter()
             .map(|s| Component {
-                name: (*s).into(),
                 permission: None,
                 intent_filters: if s == "com.win.first.MainActivity" {
                     vec![IntentFilter {
-                        action: "android.intent.action.MAIN".into(),
+                        actions: vec!["android.intent.action.MAIN".into()],
+                        categories: vec!["android.intent.category.LAUNCHER".into()],
+                        ..Default::default()
                     }]
                 } else {
                     vec![]
                 },
-                metadata: None,
+                metadata: vec![],
+                launch_mode: (s == "com.win.first.MainActivity").then_some(LaunchMode::SingleTop),
+                ..component(s)
             })
             .collect::<Vec<_>>()
         );
@@ -212,64 +491,115 @@ mod tests {
             .unwrap();
         let manifest = parse(&buf).unwrap().unwrap();
         assert_eq!(manifest.package, Some("com.asnad.nightparty".into()));
+        assert_eq!(manifest.version_name, Some("1.1".into()));
         assert_eq!(
-            manifest.permissions,
-            vec![
-                "INTERNET".into(),
-                "RECEIVE_SMS".into(),
-                "READ_PHONE_STATE".into(),
-                "READ_SMS".into(),
-                "FOREGROUND_SERVICE".into(),
-                "POST_NOTIFICATIONS".into(),
-                "WRITE_EXTERNAL_STORAGE".into(),
-                "ACCESS_NETWORK_STATE".into()
-            ]
-            .into_iter()
-            .collect()
+            manifest.sdk,
+            UsesSdk {
+                min: Some(24),
+                target: Some(33),
+                max: None
+            }
+        );
+        assert_eq!(
+            manifest.application.name,
+            Some("com.asnad.nightparty.MyApp".into())
+        );
+        assert_eq!(manifest.application.debuggable, Some(true));
+        assert_eq!(manifest.application.allow_backup, Some(true));
+        assert_eq!(manifest.application.extract_native_libs, Some(false));
+        assert_eq!(
+            permission_names(&manifest),
+            HashSet::from([
+                "android.permission.INTERNET",
+                "android.permission.RECEIVE_SMS",
+                "android.permission.READ_PHONE_STATE",
+                "android.permission.READ_SMS",
+                "android.permission.FOREGROUND_SERVICE",
+                "android.permission.POST_NOTIFICATIONS",
+                "android.permission.WRITE_EXTERNAL_STORAGE",
+                "android.permission.ACCESS_NETWORK_STATE",
+                "com.asnad.nightparty.DYNAMIC_RECEIVER_NOT_EXPORTED_PERMISSION"
+            ])
+        );
+        assert_eq!(
+            manifest.features,
+            vec![UsesFeature {
+                name: Some("android.hardware.telephony".into()),
+                gl_es_version: None,
+                required: false,
+            }]
+        );
+        assert_eq!(
+            manifest.declared_permissions,
+            vec![PermissionDeclaration {
+                name: "com.asnad.nightparty.DYNAMIC_RECEIVER_NOT_EXPORTED_PERMISSION".into(),
+                kind: DeclarationKind::Permission,
+                protection_level: Some(ProtectionLevel {
+                    base: ProtectionBase::Signature,
+                    flags: vec![],
+                }),
+                permission_group: None,
+            }]
         );
         assert_eq!(
             manifest.activities,
             [
-                ".Last2",
-                ".LastPage",
-                ".SplashActivity",
-                ".MainActivity",
+                "com.asnad.nightparty.Last2",
+                "com.asnad.nightparty.LastPage",
+                "com.asnad.nightparty.SplashActivity",
+                "com.asnad.nightparty.MainActivity",
                 "com.google.android.gms.common.api.GoogleApiActivity"
             ]
             .into_iter()
             .map(|s| Component {
-                name: s.into(),
                 permission: None,
-                intent_filters: if s == ".SplashActivity" {
+                intent_filters: if s == "com.asnad.nightparty.SplashActivity" {
                     vec![IntentFilter {
-                        action: "android.intent.action.MAIN".into(),
+                        actions: vec!["android.intent.action.MAIN".into()],
+                        categories: vec!["android.intent.category.LAUNCHER".into()],
+                        ..Default::default()
                     }]
                 } else {
                     vec![]
                 },
-                metadata: None,
+                metadata: vec![],
+                exported: Some(false),
+                launch_mode: (s == "com.asnad.nightparty.Last2").then_some(LaunchMode::SingleTask),
+                ..component(s)
             })
             .collect::<Vec<_>>()
         );
         assert_eq!(
             manifest.services,
             [
-                ".SmsSerivce",
+                "com.asnad.nightparty.SmsSerivce",
                 "com.google.firebase.components.ComponentDiscoveryService"
             ]
             .into_iter()
             .map(|s| Component {
-                name: s.into(),
                 permission: None,
                 intent_filters: vec![],
                 metadata: if s == "com.google.firebase.components.ComponentDiscoveryService" {
-                    Some(Metadata {
-                        name: "com.google.firebase.components:com.google.firebase.database.DatabaseRegistrar"
-                            .into(),
+                    [
+                        "com.google.firebase.components:com.google.firebase.database.ktx.FirebaseDatabaseKtxRegistrar",
+                        "com.google.firebase.components:com.google.firebase.ktx.FirebaseCommonKtxRegistrar",
+                        "com.google.firebase.components:com.google.firebase.database.DatabaseRegistrar",
+                    ]
+                    .into_iter()
+                    .map(|name| Metadata {
+                        name: name.into(),
+                        value: Some(MetadataValue::String(
+                            "com.google.firebase.components.ComponentRegistrar".into(),
+                        )),
+                        resource: None,
                     })
+                    .collect()
                 } else {
-                    None
+                    vec![]
                 },
+                exported: (s != "com.asnad.nightparty.SmsSerivce").then_some(false),
+                direct_boot_aware: (s != "com.asnad.nightparty.SmsSerivce").then_some(true),
+                ..component(s)
             })
             .collect::<Vec<_>>()
         );
@@ -277,31 +607,42 @@ mod tests {
             manifest.receivers,
             vec![
                 Component {
-                    name: ".SmsReceiver".into(),
                     permission: Some("android.permission.BROADCAST_SMS".into()),
                     intent_filters: vec![IntentFilter {
-                        action: "android.provider.Telephony.SMS_RECEIVED".into(),
+                        actions: vec!["android.provider.Telephony.SMS_RECEIVED".into()],
+                        ..Default::default()
                     }],
-                    metadata: None,
+                    metadata: vec![],
+                    exported: Some(false),
+                    ..component("com.asnad.nightparty.SmsReceiver")
                 },
                 Component {
-                    name: "androidx.profileinstaller.ProfileInstallReceiver".into(),
                     permission: Some("android.permission.DUMP".into()),
                     intent_filters: vec![
                         IntentFilter {
-                            action: "androidx.profileinstaller.action.INSTALL_PROFILE".into()
+                            actions: vec!["androidx.profileinstaller.action.INSTALL_PROFILE".into()],
+                            ..Default::default()
                         },
                         IntentFilter {
-                            action: "androidx.profileinstaller.action.SKIP_FILE".into()
+                            actions: vec!["androidx.profileinstaller.action.SKIP_FILE".into()],
+                            ..Default::default()
                         },
                         IntentFilter {
-                            action: "androidx.profileinstaller.action.SAVE_PROFILE".into()
+                            actions: vec!["androidx.profileinstaller.action.SAVE_PROFILE".into()],
+                            ..Default::default()
                         },
                         IntentFilter {
-                            action: "androidx.profileinstaller.action.BENCHMARK_OPERATION".into()
+                            actions: vec![
+                                "androidx.profileinstaller.action.BENCHMARK_OPERATION".into()
+                            ],
+                            ..Default::default()
                         }
                     ],
-                    metadata: None,
+                    metadata: vec![],
+                    exported: Some(true),
+                    enabled: Some(true),
+                    direct_boot_aware: Some(false),
+                    ..component("androidx.profileinstaller.ProfileInstallReceiver")
                 },
             ]
         );
@@ -313,18 +654,400 @@ mod tests {
             ]
             .into_iter()
             .map(|s| Component {
-                name: (*s).into(),
                 permission: None,
                 intent_filters: vec![],
                 metadata: if s == "androidx.startup.InitializationProvider" {
-                    Some(Metadata {
-                        name: "androidx.profileinstaller.ProfileInstallerInitializer".into(),
+                    [
+                        "androidx.emoji2.text.EmojiCompatInitializer",
+                        "androidx.lifecycle.ProcessLifecycleInitializer",
+                        "androidx.profileinstaller.ProfileInstallerInitializer",
+                    ]
+                    .into_iter()
+                    .map(|name| Metadata {
+                        name: name.into(),
+                        value: Some(MetadataValue::String("androidx.startup".into())),
+                        resource: None,
                     })
+                    .collect()
                 } else {
-                    None
+                    vec![]
                 },
+                exported: Some(false),
+                direct_boot_aware: (s == "com.google.firebase.provider.FirebaseInitProvider")
+                    .then_some(true),
+                authorities: vec![
+                    if s == "com.google.firebase.provider.FirebaseInitProvider" {
+                        "com.asnad.nightparty.firebaseinitprovider".into()
+                    } else {
+                        "com.asnad.nightparty.androidx-startup".into()
+                    }
+                ],
+                ..component(s)
             })
             .collect::<Vec<_>>()
         );
+        assert_eq!(
+            manifest.application.metadata,
+            vec![Metadata {
+                name: "com.google.android.gms.version".into(),
+                value: Some(MetadataValue::Reference(ResourceRef::Id(0x7f090008))),
+                resource: None,
+            }]
+        );
+    }
+
+    #[test]
+    fn test_parse_d() {
+        let mut buf = Vec::new();
+        File::open("tests/manifest/d.xml")
+            .unwrap()
+            .read_to_end(&mut buf)
+            .unwrap();
+        let manifest = parse(&buf).unwrap().unwrap();
+        assert_eq!(manifest.version_code, Some(42));
+        assert_eq!(manifest.shared_user_id, Some("com.example.shared".into()));
+        assert_eq!(
+            manifest.install_location,
+            Some(InstallLocation::PreferExternal)
+        );
+        assert_eq!(
+            manifest.sdk,
+            UsesSdk {
+                min: Some(21),
+                target: Some(33),
+                max: Some(34)
+            }
+        );
+        assert_eq!(
+            manifest.application,
+            Application {
+                name: Some(".RichApp".into()),
+                label: Some(TextValue::Reference(ResourceRef::Id(0x7f100000))),
+                icon: None,
+                debuggable: Some(true),
+                allow_backup: Some(false),
+                uses_cleartext_traffic: Some(true),
+                network_security_config: Some(ResourceRef::Id(0x7f150001)),
+                test_only: Some(true),
+                extract_native_libs: Some(false),
+                metadata: manifest.application.metadata.clone(),
+            }
+        );
+        assert_eq!(
+            manifest.activities[0].intent_filters,
+            vec![
+                IntentFilter {
+                    actions: vec!["android.intent.action.MAIN".into()],
+                    categories: vec!["android.intent.category.LAUNCHER".into()],
+                    priority: Some(100),
+                    ..Default::default()
+                },
+                IntentFilter {
+                    actions: vec!["android.intent.action.VIEW".into()],
+                    categories: vec![
+                        "android.intent.category.DEFAULT".into(),
+                        "android.intent.category.BROWSABLE".into()
+                    ],
+                    data: vec![
+                        IntentData {
+                            scheme: Some("https".into()),
+                            host: Some("example.com".into()),
+                            path_prefix: Some("/open".into()),
+                            ..Default::default()
+                        },
+                        IntentData {
+                            scheme: Some("rich".into()),
+                            ..Default::default()
+                        }
+                    ],
+                    priority: None,
+                    auto_verify: true,
+                    order: Some(1),
+                }
+            ]
+        );
+        assert_eq!(
+            manifest.receivers[0].intent_filters,
+            vec![IntentFilter {
+                actions: vec!["android.provider.Telephony.SMS_RECEIVED".into()],
+                priority: Some(999),
+                ..Default::default()
+            }]
+        );
+        assert_eq!(
+            manifest.receivers[0].metadata,
+            vec![Metadata {
+                name: "count".into(),
+                value: Some(MetadataValue::Integer(3)),
+                resource: None,
+            }]
+        );
+        assert_eq!(
+            manifest.application.metadata,
+            vec![
+                Metadata {
+                    name: "com.google.android.geo.API_KEY".into(),
+                    value: Some(MetadataValue::String("AIzaFakeKey".into())),
+                    resource: None,
+                },
+                Metadata {
+                    name: "firebase_analytics_collection_enabled".into(),
+                    value: Some(MetadataValue::Boolean(false)),
+                    resource: None,
+                }
+            ]
+        );
+
+        assert_eq!(
+            manifest.permissions[2],
+            UsesPermission {
+                name: "android.permission.READ_EXTERNAL_STORAGE".into(),
+                max_sdk_version: Some(32),
+                sdk_23: false,
+            }
+        );
+        assert_eq!(
+            manifest.permissions[4],
+            UsesPermission {
+                name: "android.permission.ACCESS_FINE_LOCATION".into(),
+                max_sdk_version: None,
+                sdk_23: true,
+            }
+        );
+        assert!(manifest.has_permission("com.google.android.c2dm.permission.RECEIVE"));
+        assert!(!manifest.has_permission("RECEIVE_SMS"));
+        assert_eq!(
+            manifest.declared_permissions,
+            vec![
+                PermissionDeclaration {
+                    name: "com.example.rich.permission.C2D_MESSAGE".into(),
+                    kind: DeclarationKind::Permission,
+                    protection_level: Some(ProtectionLevel {
+                        base: ProtectionBase::Signature,
+                        flags: vec![],
+                    }),
+                    permission_group: Some("com.example.rich.group".into()),
+                },
+                PermissionDeclaration {
+                    name: "com.example.rich.permission.BIND".into(),
+                    kind: DeclarationKind::Permission,
+                    protection_level: Some(ProtectionLevel {
+                        base: ProtectionBase::Signature,
+                        flags: vec!["privileged".into()],
+                    }),
+                    permission_group: None,
+                },
+                PermissionDeclaration {
+                    name: "com.example.rich.group".into(),
+                    kind: DeclarationKind::Group,
+                    protection_level: None,
+                    permission_group: None,
+                },
+                PermissionDeclaration {
+                    name: "com.example.rich.tree".into(),
+                    kind: DeclarationKind::Tree,
+                    protection_level: None,
+                    permission_group: None,
+                },
+            ]
+        );
+
+        assert_eq!(
+            manifest.features,
+            vec![
+                UsesFeature {
+                    name: Some("android.hardware.camera".into()),
+                    gl_es_version: None,
+                    required: false,
+                },
+                UsesFeature {
+                    name: None,
+                    gl_es_version: Some(0x20000),
+                    required: true,
+                }
+            ]
+        );
+        assert_eq!(
+            manifest.libraries,
+            vec![UsesLibrary {
+                name: "org.apache.http.legacy".into(),
+                required: false,
+            }]
+        );
+        assert_eq!(
+            manifest.queries,
+            Queries {
+                packages: vec!["com.whatsapp".into()],
+                intents: vec![IntentFilter {
+                    actions: vec!["android.intent.action.SEND".into()],
+                    data: vec![IntentData {
+                        mime_type: Some("text/plain".into()),
+                        ..Default::default()
+                    }],
+                    ..Default::default()
+                }],
+                providers: vec!["com.facebook.katana.provider".into()],
+            }
+        );
+        assert_eq!(
+            manifest.instrumentations,
+            vec![Instrumentation {
+                name: "androidx.test.runner.AndroidJUnitRunner".into(),
+                target_package: Some("com.example.rich".into()),
+                functional_test: Some(false),
+                handle_profiling: None,
+            }]
+        );
+
+        let alias = &manifest.activity_aliases[0];
+        assert_eq!(
+            *alias,
+            Component {
+                intent_filters: vec![IntentFilter {
+                    actions: vec!["android.intent.action.MAIN".into()],
+                    categories: vec!["android.intent.category.LAUNCHER".into()],
+                    ..Default::default()
+                }],
+                permission: Some("com.example.rich.permission.BIND".into()),
+                exported: Some(true),
+                enabled: Some(false),
+                declared_name: ".Launcher".into(),
+                target_activity: Some("com.example.rich.MainActivity".into()),
+                ..component("com.example.rich.Launcher")
+            }
+        );
+        assert_eq!(manifest.alias_target(alias), Some(&manifest.activities[0]));
+        assert_eq!(manifest.alias_target(&manifest.activities[0]), None);
+
+        let main = &manifest.activities[0];
+        assert_eq!(main.name, "com.example.rich.MainActivity");
+        assert_eq!(main.declared_name, ".MainActivity");
+        assert_eq!(main.descriptor, "Lcom/example/rich/MainActivity;");
+        assert_eq!(
+            manifest.activities[1].descriptor,
+            "Lcom/example/rich/ui/Outer$Inner;"
+        );
+        assert_eq!(main.exported, Some(true));
+        assert_eq!(main.launch_mode, Some(LaunchMode::SingleTop));
+        assert_eq!(main.task_affinity, Some("com.example.rich.main".into()));
+        assert_eq!(
+            manifest.services[0],
+            Component {
+                permission: Some("com.example.rich.permission.BIND".into()),
+                exported: Some(false),
+                process: Some(":sync".into()),
+                direct_boot_aware: Some(true),
+                foreground_service_types: vec!["dataSync".into()],
+                ..component("com.example.rich.SyncService")
+            }
+        );
+        let provider = &manifest.providers[0];
+        assert_eq!(
+            provider.authorities,
+            vec!["com.example.rich.files", "com.example.rich.other"]
+        );
+        assert_eq!(provider.grant_uri_permissions, Some(true));
+        assert_eq!(
+            provider.read_permission,
+            Some("com.example.rich.READ".into())
+        );
+        assert_eq!(
+            provider.write_permission,
+            Some("com.example.rich.WRITE".into())
+        );
+
+        // Implicitly exported through its intent filter
+        let receiver = &manifest.receivers[0];
+        assert_eq!(receiver.exported, None);
+        assert!(receiver.is_exported(ComponentKind::Receiver, Some(30)));
+        assert!(manifest.is_exported(ComponentKind::Receiver, receiver));
+        assert!(!manifest.activities[1].is_exported(ComponentKind::Activity, Some(30)));
+        assert!(!provider.is_exported(ComponentKind::Provider, Some(16)));
+        let implicit_provider = Component::default();
+        assert!(implicit_provider.is_exported(ComponentKind::Provider, Some(16)));
+        assert!(!implicit_provider.is_exported(ComponentKind::Provider, Some(17)));
+    }
+
+    #[test]
+    fn test_parse_text() {
+        let mut binary = Vec::new();
+        File::open("tests/manifest/d.xml")
+            .unwrap()
+            .read_to_end(&mut binary)
+            .unwrap();
+        let mut text = Vec::new();
+        File::open("tests/manifest/d_text.xml")
+            .unwrap()
+            .read_to_end(&mut text)
+            .unwrap();
+        let binary = parse(&binary).unwrap().unwrap();
+        let mut text = parse(&text).unwrap().unwrap();
+
+        // Names of the enum and flag values
+        assert_eq!(text.install_location, Some(InstallLocation::PreferExternal));
+        assert_eq!(text.install_location, binary.install_location);
+        let levels = |m: &Manifest| {
+            m.declared_permissions
+                .iter()
+                .map(|p| p.protection_level.clone())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            levels(&text)[1],
+            Some(ProtectionLevel {
+                base: ProtectionBase::Signature,
+                flags: vec!["privileged".into()],
+            })
+        );
+        assert_eq!(levels(&text), levels(&binary));
+        assert_eq!(text.activities[0].launch_mode, Some(LaunchMode::SingleTop));
+        assert_eq!(
+            text.activities[0].launch_mode,
+            binary.activities[0].launch_mode
+        );
+        assert_eq!(text.services[0].foreground_service_types, ["dataSync"]);
+        assert_eq!(
+            text.services[0].foreground_service_types,
+            binary.services[0].foreground_service_types
+        );
+
+        // References by name instead of id
+        assert_eq!(
+            text.application.label,
+            Some(TextValue::Reference(ResourceRef::Name(
+                "string/app_name".into()
+            )))
+        );
+        assert_eq!(
+            text.application.network_security_config,
+            Some(ResourceRef::Name("xml/network_security_config".into()))
+        );
+        let resource = &mut text.providers[0].metadata[0].resource;
+        assert_eq!(*resource, Some(ResourceRef::Name("xml/file_paths".into())));
+
+        // Same manifest otherwise
+        *resource = binary.providers[0].metadata[0].resource.clone();
+        text.application.label = binary.application.label.clone();
+        text.application.network_security_config =
+            binary.application.network_security_config.clone();
+        assert_eq!(text, binary);
+
+        assert!(parse(b"<manifest><application></manifest>").is_err());
+    }
+
+    #[test]
+    fn test_parse_proto() {
+        let mut binary = Vec::new();
+        File::open("tests/manifest/d.xml")
+            .unwrap()
+            .read_to_end(&mut binary)
+            .unwrap();
+        let mut proto = Vec::new();
+        File::open("tests/manifest/d.pb")
+            .unwrap()
+            .read_to_end(&mut proto)
+            .unwrap();
+        assert_eq!(parse_proto(&proto).unwrap(), parse(&binary).unwrap());
+        assert!(parse_proto(&proto[..100]).is_err());
     }
 }
@@ -0,0 +1,171 @@
+use serde::Serialize;
+
+use crate::{manifest::value::parse_int, xml::RawElement};
+
+/// A `<uses-permission>` or `<uses-permission-sdk-23>` element
+#[derive(Debug, Clone, Serialize, PartialEq, Eq)]
+pub struct UsesPermission {
+    /// Fully qualified name (e.g. `android.permission.INTERNET`)
+    pub name: String,
+    /// `android:maxSdkVersion`, the permission is not requested on higher API levels
+    pub max_sdk_version: Option<i32>,
+    /// Declared with `<uses-permission-sdk-23>`, only requested from API 23
+    pub sdk_23: bool,
+}
+
+impl UsesPermission {
+    pub(crate) fn parse(mut value: RawElement, sdk_23: bool) -> Option<Self> {
+        Some(UsesPermission {
+            name: value.attributes.remove("android:name")?,
+            max_sdk_version: value
+                .attributes
+                .remove("android:maxSdkVersion")
+                .and_then(|v| parse_int(&v)),
+            sdk_23,
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+pub enum DeclarationKind {
+    /// `<permission>`
+    Permission,
+    /// `<permission-group>`
+    Group,
+    /// `<permission-tree>`
+    Tree,
+}
+
+/// A permission, permission group or permission tree defined by the app
+#[derive(Debug, Clone, Serialize, PartialEq, Eq)]
+pub struct PermissionDeclaration {
+    pub name: String,
+    pub kind: DeclarationKind,
+    /// `android:protectionLevel`, only for permissions
+    pub protection_level: Option<ProtectionLevel>,
+    /// `android:permissionGroup`, only for permissions
+    pub permission_group: Option<String>,
+}
+
+impl PermissionDeclaration {
+    pub(crate) fn parse(mut value: RawElement, kind: DeclarationKind) -> Option<Self> {
+        Some(PermissionDeclaration {
+            name: value.attributes.remove("android:name")?,
+            kind,
+            protection_level: match kind {
+                DeclarationKind::Permission => Some(
+                    value
+                        .attributes
+                        .remove("android:protectionLevel")
+                        .and_then(|v| ProtectionLevel::parse(&v))
+                        .unwrap_or_default(),
+                ),
+                _ => None,
+            },
+            permission_group: value.attributes.remove("android:permissionGroup"),
+        })
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+pub enum ProtectionBase {
+    #[default]
+    Normal,
+    Dangerous,
+    Signature,
+    SignatureOrSystem,
+    Internal,
+}
+
+/// Base protection level together with its flags (e.g. `signature|privileged`)
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize)]
+pub struct ProtectionLevel {
+    pub base: ProtectionBase,
+    pub flags: Vec<String>,
+}
+
+const BASES: [(u32, &str, ProtectionBase); 5] = [
+    (0x0, "normal", ProtectionBase::Normal),
+    (0x1, "dangerous", ProtectionBase::Dangerous),
+    (0x2, "signature", ProtectionBase::Signature),
+    (0x3, "signatureOrSystem", ProtectionBase::SignatureOrSystem),
+    (0x4, "internal", ProtectionBase::Internal),
+];
+
+const FLAGS: [(u32, &str); 18] = [
+    (0x10, "privileged"),
+    (0x20, "development"),
+    (0x40, "appop"),
+    (0x80, "pre23"),
+    (0x100, "installer"),
+    (0x200, "verifier"),
+    (0x400, "preinstalled"),
+    (0x800, "setup"),
+    (0x1000, "instant"),
+    (0x2000, "runtime"),
+    (0x4000, "oem"),
+    (0x8000, "vendorPrivileged"),
+    (0x10000, "textClassifier"),
+    (0x20000, "configurator"),
+    (0x40000, "incidentReportApprover"),
+    (0x80000, "appPredictor"),
+    (0x100000, "companion"),
+    (0x200000, "retailDemo"),
+];
+
+impl ProtectionLevel {
+    /// Parses the flags of a binary manifest or the `|` separated names of a text one
+    fn parse(value: &str) -> Option<Self> {
+        match parse_int(value) {
+            Some(level) => {
+                let level = level as u32;
+                let (_, _, base) = BASES.iter().find(|(b, _, _)| *b == level & 0xf)?;
+                Some(ProtectionLevel {
+                    base: *base,
+                    flags: FLAGS
+                        .iter()
+                        .filter(|(flag, _)| level & flag != 0)
+                        .map(|(_, name)| name.to_string())
+                        .collect(),
+                })
+            }
+            None => {
+                let mut level = ProtectionLevel::default();
+                for name in value.split('|').map(str::trim) {
+                    match BASES.iter().find(|(_, n, _)| *n == name) {
+                        Some((_, _, base)) => level.base = *base,
+                        None => level.flags.push(name.to_string()),
+                    }
+                }
+                Some(level)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ProtectionBase, ProtectionLevel};
+
+    #[test]
+    fn test_protection_level() {
+        assert_eq!(
+            ProtectionLevel::parse("0x12"),
+            Some(ProtectionLevel {
+                base: ProtectionBase::Signature,
+                flags: vec!["privileged".into()],
+            })
+        );
+        assert_eq!(
+            ProtectionLevel::parse("signature|privileged"),
+            ProtectionLevel::parse("18")
+        );
+        assert_eq!(
+            ProtectionLevel::parse("dangerous"),
+            Some(ProtectionLevel {
+                base: ProtectionBase::Dangerous,
+                flags: vec![],
+            })
+        );
+    }
+}
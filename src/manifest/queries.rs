@@ -0,0 +1,41 @@
+use serde::Serialize;
+
+use crate::{manifest::intent_filter::IntentFilter, xml::RawElement};
+
+/// The `<queries>` elements, other apps the app needs to see since API 30 (package visibility)
+#[derive(Debug, Default, Serialize, PartialEq, Eq)]
+pub struct Queries {
+    /// Package names of `<package>` entries
+    pub packages: Vec<String>,
+    /// `<intent>` entries, apps handling a matching intent become visible
+    pub intents: Vec<IntentFilter>,
+    /// Authorities of `<provider>` entries
+    pub providers: Vec<String>,
+}
+
+impl Queries {
+    /// Adds the entries of a `<queries>` element, a manifest may declare several of them
+    pub(crate) fn extend(&mut self, value: RawElement) {
+        for mut element in value.children {
+            match element.tag.as_str() {
+                "package" => self
+                    .packages
+                    .extend(element.attributes.remove("android:name")),
+                "intent" => self.intents.push(element.into()),
+                "provider" => self.providers.extend(
+                    element
+                        .attributes
+                        .remove("android:authorities")
+                        .iter()
+                        .flat_map(|a| a.split(';'))
+                        .map(|a| a.trim().to_string()),
+                ),
+                _ => {}
+            }
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.packages.is_empty() && self.intents.is_empty() && self.providers.is_empty()
+    }
+}
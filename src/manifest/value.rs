@@ -0,0 +1,104 @@
+//! Parsing of attribute values, binary manifests store typed values which are rendered as strings
+//! (e.g. `true` may come out as `true`, `-1` or `0xffffffff`).
+
+use serde::Serialize;
+
+/// Reference to a resource, binary manifests hold its id while text manifests hold its name
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize)]
+pub enum ResourceRef {
+    /// Resource id, e.g. `0x7f150000`
+    Id(u32),
+    /// Resource name without the `@`, e.g. `xml/file_paths` or `android:style/Theme`
+    Name(String),
+}
+
+/// A string attribute which may be a literal or a reference to a string resource
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize)]
+pub enum TextValue {
+    Literal(String),
+    Reference(ResourceRef),
+}
+
+impl From<String> for TextValue {
+    fn from(value: String) -> Self {
+        match parse_reference(&value) {
+            Some(reference) => TextValue::Reference(reference),
+            None => TextValue::Literal(value),
+        }
+    }
+}
+
+/// Parses a boolean attribute
+pub(crate) fn parse_bool(value: &str) -> Option<bool> {
+    match value.trim() {
+        "true" | "TRUE" | "True" => Some(true),
+        "false" | "FALSE" | "False" => Some(false),
+        other => parse_int(other).map(|i| i != 0),
+    }
+}
+
+/// Parses a decimal or hexadecimal (`0x` prefixed) integer attribute
+pub(crate) fn parse_int(value: &str) -> Option<i32> {
+    let value = value.trim();
+    match value
+        .strip_prefix("0x")
+        .or_else(|| value.strip_prefix("0X"))
+    {
+        Some(hex) => u32::from_str_radix(hex, 16).ok().map(|u| u as i32),
+        None => value.parse().ok(),
+    }
+}
+
+/// Parses a resource reference (`@0x7f150000`, `@ref/0x7f150000`, `@7f150000` or `@xml/file_paths`)
+pub(crate) fn parse_reference(value: &str) -> Option<ResourceRef> {
+    let reference = value.trim().strip_prefix('@')?;
+    let id = reference.strip_prefix("ref/").unwrap_or(reference);
+    let id = id.strip_prefix("0x").unwrap_or(id);
+    if id.len() == 8 && id.bytes().all(|b| b.is_ascii_hexdigit()) {
+        u32::from_str_radix(id, 16).ok().map(ResourceRef::Id)
+    } else if !id.is_empty() && id.bytes().all(|b| b.is_ascii_digit()) {
+        id.parse().ok().map(ResourceRef::Id)
+    } else if reference.contains('/') {
+        Some(ResourceRef::Name(reference.to_string()))
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_bool, parse_int, parse_reference, ResourceRef};
+
+    #[test]
+    fn test_parse_bool() {
+        assert_eq!(parse_bool("true"), Some(true));
+        assert_eq!(parse_bool("false"), Some(false));
+        assert_eq!(parse_bool("-1"), Some(true));
+        assert_eq!(parse_bool("0xffffffff"), Some(true));
+        assert_eq!(parse_bool("0"), Some(false));
+        assert_eq!(parse_bool("yes"), None);
+    }
+
+    #[test]
+    fn test_parse_int() {
+        assert_eq!(parse_int("28"), Some(28));
+        assert_eq!(parse_int("-1000"), Some(-1000));
+        assert_eq!(parse_int("0x1c"), Some(28));
+        assert_eq!(parse_int("0xffffffff"), Some(-1));
+        assert_eq!(parse_int("@0x7f040001"), None);
+    }
+
+    #[test]
+    fn test_parse_reference() {
+        let id = Some(ResourceRef::Id(0x7f150000));
+        assert_eq!(parse_reference("@0x7f150000"), id);
+        assert_eq!(parse_reference("@ref/0x7f150000"), id);
+        assert_eq!(parse_reference("@7f150000"), id);
+        assert_eq!(parse_reference("@2132082688"), id);
+        assert_eq!(
+            parse_reference("@xml/file_paths"),
+            Some(ResourceRef::Name("xml/file_paths".into()))
+        );
+        assert_eq!(parse_reference("com.example"), None);
+    }
+}
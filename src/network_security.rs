@@ -0,0 +1,389 @@
+//! Network security configuration (`android:networkSecurityConfig`), see
+//! <https://developer.android.com/privacy-and-security/security-config>
+
+use serde::Serialize;
+
+use crate::{
+    finding::{Finding, Severity},
+    manifest::value::{parse_bool, ResourceRef},
+    resources::{value::ResValue, ResourceTable},
+    xml::{XmlElement, XmlValue},
+};
+
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize)]
+pub struct NetworkSecurityConfig {
+    /// `<base-config>`, applies to every domain without a domain config
+    pub base_config: Option<DomainConfig>,
+    pub domain_configs: Vec<DomainConfig>,
+    /// Trust anchors of `<debug-overrides>`, only used by debuggable builds
+    pub debug_overrides: Option<Vec<Certificates>>,
+}
+
+/// A `<domain-config>`, or the `<base-config>` when it has no domains.
+///
+/// Missing values are inherited from the enclosing domain config, then the base config.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize)]
+pub struct DomainConfig {
+    pub domains: Vec<Domain>,
+    pub cleartext_traffic_permitted: Option<bool>,
+    pub trust_anchors: Option<Vec<Certificates>>,
+    pub pin_set: Option<PinSet>,
+    /// Nested domain configs
+    pub domain_configs: Vec<DomainConfig>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Domain {
+    pub name: String,
+    pub include_subdomains: bool,
+}
+
+/// A `<certificates>` trust anchor
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Certificates {
+    pub source: CertificateSource,
+    /// `overridePins`, certificates from this source bypass the pin set
+    pub override_pins: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub enum CertificateSource {
+    /// Preinstalled system CAs
+    System,
+    /// CAs added by the user
+    User,
+    /// Certificates bundled with the app, path of the raw resource when it resolves
+    Raw {
+        reference: ResourceRef,
+        path: Option<String>,
+    },
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize)]
+pub struct PinSet {
+    /// `expiration` date (`yyyy-MM-dd`), pinning is disabled afterwards
+    pub expiration: Option<String>,
+    pub pins: Vec<Pin>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Pin {
+    /// Digest algorithm, only `SHA-256` is supported by Android
+    pub digest: String,
+    /// Base64 encoded digest of the `SubjectPublicKeyInfo`
+    pub value: String,
+}
+
+fn parse_certificates(element: &XmlElement) -> Vec<Certificates> {
+    element
+        .children_by_tag("certificates")
+        .filter_map(|c| {
+            let source = match c.attributes.get("src")? {
+                XmlValue::Literal(s) if s == "system" => CertificateSource::System,
+                XmlValue::Literal(s) if s == "user" => CertificateSource::User,
+                XmlValue::Literal(s) => CertificateSource::Raw {
+                    reference: ResourceRef::Name(s.trim_start_matches('@').to_string()),
+                    path: None,
+                },
+                XmlValue::Resource { reference, value } => CertificateSource::Raw {
+                    reference: reference.clone(),
+                    path: match value {
+                        Some(ResValue::String(path)) => Some(path.clone()),
+                        _ => None,
+                    },
+                },
+            };
+            Some(Certificates {
+                source,
+                override_pins: c
+                    .attribute("overridePins")
+                    .and_then(parse_bool)
+                    .unwrap_or_default(),
+            })
+        })
+        .collect()
+}
+
+impl From<&XmlElement> for DomainConfig {
+    fn from(element: &XmlElement) -> Self {
+        DomainConfig {
+            domains: element
+                .children_by_tag("domain")
+                .map(|d| Domain {
+                    name: d.text.trim().to_string(),
+                    include_subdomains: d
+                        .attribute("includeSubdomains")
+                        .and_then(parse_bool)
+                        .unwrap_or_default(),
+                })
+                .collect(),
+            cleartext_traffic_permitted: element
+                .attribute("cleartextTrafficPermitted")
+                .and_then(parse_bool),
+            trust_anchors: element
+                .children_by_tag("trust-anchors")
+                .next()
+                .map(parse_certificates),
+            pin_set: element.children_by_tag("pin-set").next().map(|p| PinSet {
+                expiration: p.attribute("expiration").map(str::to_string),
+                pins: p
+                    .children_by_tag("pin")
+                    .map(|pin| Pin {
+                        digest: pin.attribute("digest").unwrap_or("SHA-256").to_string(),
+                        value: pin.text.trim().to_string(),
+                    })
+                    .collect(),
+            }),
+            domain_configs: element
+                .children_by_tag("domain-config")
+                .map(DomainConfig::from)
+                .collect(),
+        }
+    }
+}
+
+impl From<&XmlElement> for NetworkSecurityConfig {
+    fn from(root: &XmlElement) -> Self {
+        NetworkSecurityConfig {
+            base_config: root
+                .children_by_tag("base-config")
+                .next()
+                .map(DomainConfig::from),
+            domain_configs: root
+                .children_by_tag("domain-config")
+                .map(DomainConfig::from)
+                .collect(),
+            debug_overrides: root.children_by_tag("debug-overrides").next().map(|d| {
+                d.children_by_tag("trust-anchors")
+                    .flat_map(parse_certificates)
+                    .collect()
+            }),
+        }
+    }
+}
+
+impl DomainConfig {
+    fn location(&self) -> String {
+        match self.domains.first() {
+            Some(domain) => format!("domain-config[{}]", domain.name),
+            None => "base-config".to_string(),
+        }
+    }
+
+    fn findings(&self, findings: &mut Vec<Finding>) {
+        let location = self.location();
+        let base = self.domains.is_empty();
+        if self.cleartext_traffic_permitted == Some(true) {
+            findings.push(if base {
+                Finding::new(
+                    "nsc-cleartext-global",
+                    Severity::High,
+                    &location,
+                    "Cleartext traffic is permitted for every domain",
+                )
+            } else {
+                Finding::new(
+                    "nsc-cleartext-domain",
+                    Severity::Medium,
+                    &location,
+                    "Cleartext traffic is permitted for the domain",
+                )
+            });
+        }
+        for certificates in self.trust_anchors.iter().flatten() {
+            if certificates.source == CertificateSource::User {
+                findings.push(Finding::new(
+                    "nsc-user-ca",
+                    Severity::High,
+                    &location,
+                    "User-installed CAs are trusted in release builds",
+                ));
+            }
+            if certificates.override_pins {
+                findings.push(Finding::new(
+                    "nsc-override-pins",
+                    Severity::Medium,
+                    &location,
+                    "Trust anchors bypass certificate pinning",
+                ));
+            }
+        }
+        if let Some(pin_set) = &self.pin_set {
+            if pin_set.pins.len() < 2 {
+                findings.push(Finding::new(
+                    "nsc-single-pin",
+                    Severity::Low,
+                    &location,
+                    "Pin set has no backup pin",
+                ));
+            }
+            if let Some(expiration) = &pin_set.expiration {
+                findings.push(Finding::new(
+                    "nsc-pin-expiration",
+                    Severity::Info,
+                    &location,
+                    format!("Certificate pinning is disabled after {expiration}"),
+                ));
+            }
+        }
+        for domain_config in &self.domain_configs {
+            domain_config.findings(findings);
+        }
+    }
+}
+
+impl NetworkSecurityConfig {
+    /// Weaknesses of the configuration, `debug-overrides` are ignored as they do not apply
+    /// to release builds
+    pub fn findings(&self) -> Vec<Finding> {
+        let mut findings = Vec::new();
+        for config in self.base_config.iter().chain(&self.domain_configs) {
+            config.findings(&mut findings);
+        }
+        findings
+    }
+}
+
+/// Path of the configuration in the APK, file references without a resource table
+/// are assumed to follow the usual `res/<type>/<name>.xml` layout
+pub(crate) fn path(reference: &ResourceRef, resources: Option<&ResourceTable>) -> Option<String> {
+    match resources.and_then(|r| r.resolve_default(reference)) {
+        Some(ResValue::String(path)) => Some(path.clone()),
+        _ => match reference {
+            ResourceRef::Name(name) => Some(format!("res/{name}.xml")),
+            ResourceRef::Id(id) => {
+                log::warn!("Unresolved network security config {id:#010x}");
+                None
+            }
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs::File, io::Read};
+
+    use super::{
+        path, CertificateSource, Certificates, Domain, DomainConfig, NetworkSecurityConfig, Pin,
+        PinSet,
+    };
+    use crate::{finding::Severity, manifest::value::ResourceRef, xml::parse_xml};
+
+    #[test]
+    fn test_parse() {
+        let mut buf = Vec::new();
+        File::open("tests/xml/network_security_config.xml")
+            .unwrap()
+            .read_to_end(&mut buf)
+            .unwrap();
+        let root = parse_xml(&buf, None).unwrap().unwrap();
+        let config = NetworkSecurityConfig::from(&root);
+        assert_eq!(
+            config,
+            NetworkSecurityConfig {
+                base_config: Some(DomainConfig {
+                    cleartext_traffic_permitted: Some(true),
+                    trust_anchors: Some(vec![
+                        Certificates {
+                            source: CertificateSource::System,
+                            override_pins: false,
+                        },
+                        Certificates {
+                            source: CertificateSource::User,
+                            override_pins: false,
+                        },
+                    ]),
+                    ..Default::default()
+                }),
+                domain_configs: vec![DomainConfig {
+                    domains: vec![
+                        Domain {
+                            name: "example.com".into(),
+                            include_subdomains: true,
+                        },
+                        Domain {
+                            name: "api.example.org".into(),
+                            include_subdomains: false,
+                        },
+                    ],
+                    cleartext_traffic_permitted: Some(false),
+                    trust_anchors: Some(vec![Certificates {
+                        source: CertificateSource::Raw {
+                            reference: ResourceRef::Id(0x7f130000),
+                            path: None,
+                        },
+                        override_pins: true,
+                    }]),
+                    pin_set: Some(PinSet {
+                        expiration: Some("2025-01-01".into()),
+                        pins: vec![Pin {
+                            digest: "SHA-256".into(),
+                            value: "7HIpactkIAq2Y49orFOOQKurWxmmSFZhBCoQYcRhJ3Y=".into(),
+                        }],
+                    }),
+                    domain_configs: vec![DomainConfig {
+                        domains: vec![Domain {
+                            name: "legacy.example.com".into(),
+                            include_subdomains: false,
+                        }],
+                        cleartext_traffic_permitted: Some(true),
+                        ..Default::default()
+                    }],
+                }],
+                debug_overrides: Some(vec![Certificates {
+                    source: CertificateSource::User,
+                    override_pins: false,
+                }]),
+            }
+        );
+
+        let findings: Vec<_> = config
+            .findings()
+            .into_iter()
+            .map(|f| (f.rule, f.severity, f.location))
+            .collect();
+        assert_eq!(
+            findings,
+            vec![
+                (
+                    "nsc-cleartext-global".into(),
+                    Severity::High,
+                    "base-config".into()
+                ),
+                ("nsc-user-ca".into(), Severity::High, "base-config".into()),
+                (
+                    "nsc-override-pins".into(),
+                    Severity::Medium,
+                    "domain-config[example.com]".into()
+                ),
+                (
+                    "nsc-single-pin".into(),
+                    Severity::Low,
+                    "domain-config[example.com]".into()
+                ),
+                (
+                    "nsc-pin-expiration".into(),
+                    Severity::Info,
+                    "domain-config[example.com]".into()
+                ),
+                (
+                    "nsc-cleartext-domain".into(),
+                    Severity::Medium,
+                    "domain-config[legacy.example.com]".into()
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_path() {
+        assert_eq!(
+            path(
+                &ResourceRef::Name("xml/network_security_config".into()),
+                None
+            ),
+            Some("res/xml/network_security_config.xml".into())
+        );
+        assert_eq!(path(&ResourceRef::Id(0x7f150001), None), None);
+    }
+}
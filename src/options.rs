@@ -0,0 +1,261 @@
+//! Options of the parsing, the entry points of the crate with their default options delegate to
+//! the ones of [`ParseOptions`]
+
+use std::io::{Read, Seek};
+
+use crate::{dex::CallbackModel, errors::ApkParseError, Apk};
+
+/// Order of `Apk::methods`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum MethodOrder {
+    /// DFS over the call graph, starting from the components of the manifest
+    #[default]
+    Dfs,
+    /// Callers before callees over the condensed call graph, see `Apk::sort_topologically`
+    Topological,
+}
+
+/// Builder of the options of the parsing, the defaults are the ones of [`parse`](crate::parse).
+///
+/// ### Example
+/// ```no_run
+/// use dexompiler::ParseOptions;
+///
+/// // Fast pass over the manifest only, other entries are not even extracted
+/// let file = std::fs::File::open("tests/example.apk").unwrap();
+/// let apk = ParseOptions::new()
+///     .skip_code(true)
+///     .skip_signatures(true)
+///     .skip_hashes(true)
+///     .parse(file)
+///     .unwrap();
+/// println!("{:?}", apk.manifest);
+///
+/// // Opcodes of the app's own code, the 1000 first methods
+/// let file = std::fs::File::open("tests/example.apk").unwrap();
+/// let apk = ParseOptions::new()
+///     .include_package("com.example")
+///     .exclude_package("com.example.generated")
+///     .max_methods(1000)
+///     .opcodes_only(true)
+///     .parse(file)
+///     .unwrap();
+/// println!("{}", apk.methods.len());
+/// ```
+#[derive(Debug, Clone)]
+pub struct ParseOptions {
+    pub(crate) skip_manifest: bool,
+    pub(crate) skip_code: bool,
+    pub(crate) skip_signatures: bool,
+    pub(crate) skip_hashes: bool,
+    /// Packages as descriptor prefixes (e.g. `Lcom/example/`)
+    pub(crate) include: Vec<String>,
+    pub(crate) exclude: Vec<String>,
+    pub(crate) max_methods: Option<usize>,
+    pub(crate) max_instructions: Option<usize>,
+    pub(crate) opcodes_only: bool,
+    pub(crate) order: MethodOrder,
+    pub(crate) fatal_errors: bool,
+    /// Whether unreadable DEX files of an APK are fatal, see `ParseOptions::fatal_errors`
+    pub(crate) fatal_dex_errors: bool,
+    pub(crate) max_depth: usize,
+    pub(crate) callbacks: CallbackModel,
+}
+
+impl Default for ParseOptions {
+    fn default() -> Self {
+        Self {
+            skip_manifest: false,
+            skip_code: false,
+            skip_signatures: false,
+            skip_hashes: false,
+            include: Vec::new(),
+            exclude: Vec::new(),
+            max_methods: None,
+            max_instructions: None,
+            opcodes_only: false,
+            order: MethodOrder::Dfs,
+            fatal_errors: true,
+            fatal_dex_errors: false,
+            max_depth: 3,
+            callbacks: CallbackModel::default(),
+        }
+    }
+}
+
+fn recover(fatal: bool, error: ApkParseError) -> Result<(), ApkParseError> {
+    if fatal {
+        Err(error)
+    } else {
+        log::error!("{error}");
+        Ok(())
+    }
+}
+
+/// Descriptor prefix of the classes of a package and its subpackages
+fn package_prefix(package: &str) -> String {
+    format!("L{}/", package.trim_end_matches('.').replace('.', "/"))
+}
+
+impl ParseOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Skips `AndroidManifest.xml`, methods are then sorted by signature
+    pub fn skip_manifest(mut self, skip: bool) -> Self {
+        self.skip_manifest = skip;
+        self
+    }
+
+    /// Skips the DEX files, embedded payloads included
+    pub fn skip_code(mut self, skip: bool) -> Self {
+        self.skip_code = skip;
+        self
+    }
+
+    /// Skips reading and verifying the signatures, `Apk::signatures` and `Apk::verification`
+    /// are then empty
+    pub fn skip_signatures(mut self, skip: bool) -> Self {
+        self.skip_signatures = skip;
+        self
+    }
+
+    /// Skips the SHA-256 of the entries, see `ArchiveEntry::sha256`.
+    ///
+    /// Together with `skip_code` and `skip_signatures` only the entries the manifest and
+    /// resources need are extracted, the content type of the others is `Unknown`.
+    pub fn skip_hashes(mut self, skip: bool) -> Self {
+        self.skip_hashes = skip;
+        self
+    }
+
+    /// Keeps only the classes of the given packages (e.g. `com.example`) and their subpackages,
+    /// can be called several times
+    pub fn include_package(mut self, package: &str) -> Self {
+        self.include.push(package_prefix(package));
+        self
+    }
+
+    /// Drops the classes of the given package and its subpackages, even if included
+    pub fn exclude_package(mut self, package: &str) -> Self {
+        self.exclude.push(package_prefix(package));
+        self
+    }
+
+    /// Keeps the first `max` methods in the chosen order, the call graph keeps all of them
+    pub fn max_methods(mut self, max: usize) -> Self {
+        self.max_methods = Some(max);
+        self
+    }
+
+    /// Decodes up to `max` instructions per method, the calls of the next ones are not in the
+    /// call graph
+    pub fn max_instructions(mut self, max: usize) -> Self {
+        self.max_instructions = Some(max);
+        self
+    }
+
+    /// Keeps only the opcode of the instructions, without the invoked method ids
+    pub fn opcodes_only(mut self, opcodes_only: bool) -> Self {
+        self.opcodes_only = opcodes_only;
+        self
+    }
+
+    pub fn order(mut self, order: MethodOrder) -> Self {
+        self.order = order;
+        self
+    }
+
+    /// Whether malformed manifests and code fail the parsing, otherwise they are logged and
+    /// skipped.
+    ///
+    /// By default malformed manifests and bytecode are fatal while `classes*.dex` files which
+    /// cannot be read at all are logged and skipped, packed apps often ship junk ones. Setting
+    /// this option applies it to both.
+    pub fn fatal_errors(mut self, fatal: bool) -> Self {
+        self.fatal_errors = fatal;
+        self.fatal_dex_errors = fatal;
+        self
+    }
+
+    /// Nesting depth up to which embedded archives are analysed, an APK in an APK is at depth 1
+    pub fn max_depth(mut self, depth: usize) -> Self {
+        self.max_depth = depth;
+        self
+    }
+
+    /// Model linking implicit framework callbacks, see [`parse_with_callbacks`](crate::parse_with_callbacks)
+    pub fn callbacks(mut self, callbacks: CallbackModel) -> Self {
+        self.callbacks = callbacks;
+        self
+    }
+
+    /// Whether the class with the given descriptor is analysed
+    pub(crate) fn keeps_class(&self, class_type: &str) -> bool {
+        (self.include.is_empty() || self.include.iter().any(|p| class_type.starts_with(p)))
+            && !self.exclude.iter().any(|p| class_type.starts_with(p))
+    }
+
+    /// Logs an error if it is not fatal, otherwise returns it
+    pub(crate) fn recover<E: Into<ApkParseError>>(&self, error: E) -> Result<(), ApkParseError> {
+        recover(self.fatal_errors, error.into())
+    }
+
+    /// Same as `recover` for a `classes*.dex` file of an APK which cannot be read
+    pub(crate) fn recover_dex(&self, error: dex::Error) -> Result<(), ApkParseError> {
+        recover(self.fatal_dex_errors, error.into())
+    }
+
+    /// Parses an APK or an Android App Bundle, see [`parse`](crate::parse)
+    pub fn parse<R: Read + Seek>(&self, apk: R) -> Result<Apk, ApkParseError> {
+        crate::parse_archive(apk, self, 0)
+    }
+
+    /// Parses the base and split APKs of an app, see [`parse_splits`](crate::parse_splits)
+    pub fn parse_splits<R: Read + Seek>(
+        &self,
+        apks: impl IntoIterator<Item = R>,
+    ) -> Result<Apk, ApkParseError> {
+        crate::parse_split_archives(apks, self)
+    }
+
+    /// Parses a split APK set, see [`parse_bundle`](crate::parse_bundle)
+    pub fn parse_bundle<R: Read + Seek>(&self, bundle: R) -> Result<Apk, ApkParseError> {
+        crate::parse_split_bundle(bundle, self)
+    }
+
+    /// Analyses DEX files, see [`parse_dexes`](crate::parse_dexes)
+    pub fn parse_dexes<B: AsRef<[u8]>>(&self, dexes: &[B]) -> Result<Apk, ApkParseError> {
+        let dexes = crate::read_dexes(dexes.iter().map(|dex| dex.as_ref().to_vec()), self)?;
+        crate::analyse(Apk::default(), &dexes, self)
+    }
+
+    /// Analyses the DEX files of a JAR or an AAR, see [`parse_library`](crate::parse_library)
+    pub fn parse_library<R: Read + Seek>(&self, library: R) -> Result<Apk, ApkParseError> {
+        crate::parse_library_archive(library, self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ParseOptions;
+
+    #[test]
+    fn test_keeps_class() {
+        let options = ParseOptions::new()
+            .include_package("com.example")
+            .include_package("org.lib.")
+            .exclude_package("com.example.generated");
+        assert!(options.keeps_class("Lcom/example/Main;"));
+        assert!(options.keeps_class("Lcom/example/ui/Main$1;"));
+        assert!(options.keeps_class("Lorg/lib/Foo;"));
+        assert!(!options.keeps_class("Lcom/examples/Main;"));
+        assert!(!options.keeps_class("Lcom/example/generated/R;"));
+        assert!(!options.keeps_class("Landroidx/core/Foo;"));
+
+        let options = ParseOptions::new().exclude_package("androidx");
+        assert!(options.keeps_class("LMain;"));
+        assert!(!options.keeps_class("Landroidx/core/Foo;"));
+    }
+}
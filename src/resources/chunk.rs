@@ -0,0 +1,107 @@
+//! Little-endian readers for the chunks (`ResChunk_header`) shared by the binary resource formats
+
+use super::errors::ResourceError;
+
+pub(crate) const RES_STRING_POOL_TYPE: u16 = 0x0001;
+pub(crate) const RES_TABLE_TYPE: u16 = 0x0002;
+pub(crate) const RES_TABLE_PACKAGE_TYPE: u16 = 0x0200;
+pub(crate) const RES_TABLE_TYPE_TYPE: u16 = 0x0201;
+
+pub(crate) fn read_u8(buf: &[u8], offset: usize) -> Result<u8, ResourceError> {
+    buf.get(offset).copied().ok_or(ResourceError::Eof(offset))
+}
+
+pub(crate) fn read_u16(buf: &[u8], offset: usize) -> Result<u16, ResourceError> {
+    buf.get(offset..offset + 2)
+        .map(|b| u16::from_le_bytes([b[0], b[1]]))
+        .ok_or(ResourceError::Eof(offset))
+}
+
+pub(crate) fn read_u32(buf: &[u8], offset: usize) -> Result<u32, ResourceError> {
+    buf.get(offset..offset + 4)
+        .map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+        .ok_or(ResourceError::Eof(offset))
+}
+
+/// A chunk of `buf`, offsets are relative to the start of the buffer
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Chunk {
+    pub chunk_type: u16,
+    pub offset: usize,
+    pub header_size: usize,
+    pub size: usize,
+}
+
+impl Chunk {
+    pub fn read(buf: &[u8], offset: usize) -> Result<Self, ResourceError> {
+        let chunk_type = read_u16(buf, offset)?;
+        let header_size = read_u16(buf, offset + 2)? as usize;
+        let size = read_u32(buf, offset + 4)?;
+        if (size as usize) < header_size.max(8) || offset + size as usize > buf.len() {
+            return Err(ResourceError::BadChunkSize { offset, size });
+        }
+        Ok(Chunk {
+            chunk_type,
+            offset,
+            header_size,
+            size: size as usize,
+        })
+    }
+
+    /// Fails unless the chunk has the given type
+    pub fn expect(self, chunk_type: u16) -> Result<Self, ResourceError> {
+        if self.chunk_type == chunk_type {
+            Ok(self)
+        } else {
+            Err(ResourceError::UnexpectedChunk {
+                offset: self.offset,
+                chunk_type: self.chunk_type,
+            })
+        }
+    }
+
+    pub fn end(&self) -> usize {
+        self.offset + self.size
+    }
+
+    /// Bytes of the chunk, header included
+    pub fn data<'a>(&self, buf: &'a [u8]) -> &'a [u8] {
+        &buf[self.offset..self.end()]
+    }
+
+    /// Chunks following the header of this one
+    pub fn children<'a>(&self, buf: &'a [u8]) -> Children<'a> {
+        Children {
+            buf,
+            offset: self.offset + self.header_size,
+            end: self.end(),
+        }
+    }
+}
+
+/// Iterator over consecutive chunks, stops at the first malformed one
+pub(crate) struct Children<'a> {
+    buf: &'a [u8],
+    offset: usize,
+    end: usize,
+}
+
+impl Iterator for Children<'_> {
+    type Item = Result<Chunk, ResourceError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.offset + 8 > self.end {
+            return None;
+        }
+        match Chunk::read(&self.buf[..self.end], self.offset) {
+            Ok(chunk) => {
+                self.offset = chunk.end();
+                Some(Ok(chunk))
+            }
+            Err(e) => {
+                self.offset = self.end;
+                Some(Err(e))
+            }
+        }
+    }
+}
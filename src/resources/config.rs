@@ -0,0 +1,168 @@
+use std::fmt::Display;
+
+use serde::Serialize;
+
+use super::chunk::{read_u16, read_u32, read_u8};
+
+/// `ResTable_config`, the qualifiers a set of values applies to (e.g. `fr-rCA`, `night`, `hdpi`).
+///
+/// Fields missing from older, shorter configurations are left at 0 (any).
+#[derive(Debug, Default, Clone, PartialEq, Eq, Hash, Serialize)]
+pub struct Config {
+    pub mcc: u16,
+    pub mnc: u16,
+    /// ISO 639 language code, empty for any
+    pub language: String,
+    /// ISO 3166 region code, empty for any
+    pub region: String,
+    pub orientation: u8,
+    pub touchscreen: u8,
+    pub density: u16,
+    pub keyboard: u8,
+    pub navigation: u8,
+    pub input_flags: u8,
+    pub screen_width: u16,
+    pub screen_height: u16,
+    pub sdk_version: u16,
+    pub screen_layout: u8,
+    pub ui_mode: u8,
+    pub smallest_screen_width_dp: u16,
+    pub screen_width_dp: u16,
+    pub screen_height_dp: u16,
+}
+
+/// Decodes a language or region, 3 letter codes are packed in 2 bytes with the high bit set
+fn unpack_locale(bytes: [u8; 2], base: u8) -> String {
+    match bytes {
+        [0, 0] => String::new(),
+        [first, second] if first & 0x80 != 0 => [
+            second & 0x1f,
+            ((second & 0xe0) >> 5) | ((first & 0x03) << 3),
+            (first & 0x7c) >> 2,
+        ]
+        .iter()
+        .map(|&c| (base + c) as char)
+        .collect(),
+        _ => bytes.iter().map(|&c| c as char).collect(),
+    }
+}
+
+impl Config {
+    /// Parses a configuration starting with its size, fields past the size are ignored
+    pub(crate) fn parse(data: &[u8], offset: usize) -> Self {
+        let size = read_u32(data, offset).unwrap_or(0) as usize;
+        let data = data
+            .get(offset..offset + size)
+            .unwrap_or(&data[offset.min(data.len())..]);
+        let u8_at = |at: usize| read_u8(data, at).unwrap_or(0);
+        let u16_at = |at: usize| read_u16(data, at).unwrap_or(0);
+        Config {
+            mcc: u16_at(4),
+            mnc: u16_at(6),
+            language: unpack_locale([u8_at(8), u8_at(9)], b'a'),
+            region: unpack_locale([u8_at(10), u8_at(11)], b'0'),
+            orientation: u8_at(12),
+            touchscreen: u8_at(13),
+            density: u16_at(14),
+            keyboard: u8_at(16),
+            navigation: u8_at(17),
+            input_flags: u8_at(18),
+            screen_width: u16_at(20),
+            screen_height: u16_at(22),
+            sdk_version: u16_at(24),
+            screen_layout: u8_at(28),
+            ui_mode: u8_at(29),
+            smallest_screen_width_dp: u16_at(30),
+            screen_width_dp: u16_at(32),
+            screen_height_dp: u16_at(34),
+        }
+    }
+
+    /// The default configuration, used when no other one matches
+    pub fn is_default(&self) -> bool {
+        *self == Config::default()
+    }
+}
+
+/// Qualifiers of the configuration as in resource directory names (e.g. `fr-rCA-night-v21`),
+/// `default` for the default configuration. Less common qualifiers are omitted.
+impl Display for Config {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut qualifiers = Vec::new();
+        if self.mcc != 0 {
+            qualifiers.push(format!("mcc{}", self.mcc));
+        }
+        if self.mnc != 0 {
+            qualifiers.push(format!("mnc{:02}", self.mnc));
+        }
+        if !self.language.is_empty() {
+            qualifiers.push(self.language.clone());
+        }
+        if !self.region.is_empty() {
+            qualifiers.push(format!("r{}", self.region));
+        }
+        if self.smallest_screen_width_dp != 0 {
+            qualifiers.push(format!("sw{}dp", self.smallest_screen_width_dp));
+        }
+        if self.screen_width_dp != 0 {
+            qualifiers.push(format!("w{}dp", self.screen_width_dp));
+        }
+        if self.screen_height_dp != 0 {
+            qualifiers.push(format!("h{}dp", self.screen_height_dp));
+        }
+        match self.orientation {
+            1 => qualifiers.push("port".into()),
+            2 => qualifiers.push("land".into()),
+            3 => qualifiers.push("square".into()),
+            _ => {}
+        }
+        match self.ui_mode & 0x30 {
+            0x10 => qualifiers.push("notnight".into()),
+            0x20 => qualifiers.push("night".into()),
+            _ => {}
+        }
+        match self.density {
+            0 => {}
+            120 => qualifiers.push("ldpi".into()),
+            160 => qualifiers.push("mdpi".into()),
+            213 => qualifiers.push("tvdpi".into()),
+            240 => qualifiers.push("hdpi".into()),
+            320 => qualifiers.push("xhdpi".into()),
+            480 => qualifiers.push("xxhdpi".into()),
+            640 => qualifiers.push("xxxhdpi".into()),
+            0xfffe => qualifiers.push("anydpi".into()),
+            0xffff => qualifiers.push("nodpi".into()),
+            other => qualifiers.push(format!("{other}dpi")),
+        }
+        if self.sdk_version != 0 {
+            qualifiers.push(format!("v{}", self.sdk_version));
+        }
+
+        if qualifiers.is_empty() {
+            write!(f, "default")
+        } else {
+            write!(f, "{}", qualifiers.join("-"))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{unpack_locale, Config};
+
+    #[test]
+    fn test_qualifiers() {
+        assert_eq!(Config::default().to_string(), "default");
+        let config = Config {
+            language: "fr".into(),
+            region: "CA".into(),
+            ui_mode: 0x20,
+            density: 480,
+            sdk_version: 21,
+            ..Default::default()
+        };
+        assert_eq!(config.to_string(), "fr-rCA-night-xxhdpi-v21");
+        // "fil" packed as in ResTable_config::packLanguage
+        assert_eq!(unpack_locale([0xad, 0x05], b'a'), "fil");
+    }
+}
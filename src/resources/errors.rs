@@ -0,0 +1,13 @@
+use thiserror::Error;
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum ResourceError {
+    #[error("Unexpected end of data at offset {0}")]
+    Eof(usize),
+    #[error("Unexpected chunk type {chunk_type:#06x} at offset {offset}")]
+    UnexpectedChunk { offset: usize, chunk_type: u16 },
+    #[error("Invalid chunk size {size} at offset {offset}")]
+    BadChunkSize { offset: usize, size: u32 },
+    #[error("String index {0} is out of bounds")]
+    StringIndex(u32),
+}
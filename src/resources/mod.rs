@@ -0,0 +1,532 @@
+//! Parser of the compiled resource table (`resources.arsc`)
+
+pub(crate) mod chunk;
+pub(crate) mod config;
+pub(crate) mod errors;
+pub(crate) mod string_pool;
+pub(crate) mod value;
+
+use std::collections::BTreeMap;
+
+use serde::Serialize;
+
+use self::{
+    chunk::{
+        read_u16, read_u32, read_u8, Chunk, RES_STRING_POOL_TYPE, RES_TABLE_PACKAGE_TYPE,
+        RES_TABLE_TYPE, RES_TABLE_TYPE_TYPE,
+    },
+    config::Config,
+    errors::ResourceError,
+    string_pool::StringPool,
+    value::ResValue,
+};
+use crate::manifest::value::ResourceRef;
+
+const FLAG_SPARSE: u8 = 0x01;
+const FLAG_OFFSET16: u8 = 0x02;
+const FLAG_COMPLEX: u16 = 0x0001;
+const FLAG_COMPACT: u16 = 0x0008;
+const NO_ENTRY: u32 = 0xffffffff;
+
+/// Maximum number of references followed when resolving a value
+const MAX_REFERENCE_DEPTH: usize = 16;
+
+/// Resources of an APK, as compiled in `resources.arsc`
+#[derive(Debug, Default, Serialize)]
+pub struct ResourceTable {
+    pub packages: Vec<Package>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Package {
+    /// Package id, the upper byte of resource ids (`0x7f` for apps)
+    pub id: u8,
+    pub name: String,
+    pub types: Vec<ResourceType>,
+}
+
+/// A type of resources (e.g. `string`, `xml`) with its values for every configuration
+#[derive(Debug, Serialize)]
+pub struct ResourceType {
+    pub id: u8,
+    pub name: String,
+    pub configs: Vec<TypeConfig>,
+}
+
+/// Entries of a type for one configuration, keyed by entry index
+#[derive(Debug, Serialize)]
+pub struct TypeConfig {
+    pub config: Config,
+    pub entries: BTreeMap<u16, Entry>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Entry {
+    /// Name of the entry (e.g. `app_name`)
+    pub key: String,
+    pub value: EntryValue,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub enum EntryValue {
+    Simple(ResValue),
+    /// Bags such as styles, arrays and plurals, `(attribute id, value)` pairs
+    Complex {
+        parent: u32,
+        items: Vec<(u32, ResValue)>,
+    },
+}
+
+/// A value of a resource in a given configuration, see `ResourceTable::resolve`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ResolvedValue<'a> {
+    pub config: &'a Config,
+    pub value: &'a ResValue,
+}
+
+impl ResourceTable {
+    pub fn parse(buf: &[u8]) -> Result<Self, ResourceError> {
+        let table = Chunk::read(buf, 0)?.expect(RES_TABLE_TYPE)?;
+        let mut strings = StringPool::default();
+        let mut packages = Vec::new();
+        for chunk in table.children(buf) {
+            let chunk = chunk?;
+            match chunk.chunk_type {
+                RES_STRING_POOL_TYPE => strings = StringPool::parse(buf, chunk)?,
+                RES_TABLE_PACKAGE_TYPE => packages.push(Package::parse(buf, chunk, &strings)?),
+                other => log::debug!("Skipping table chunk {other:#06x}"),
+            }
+        }
+        Ok(ResourceTable { packages })
+    }
+
+    /// Adds the packages, types and configurations of `other` (e.g. the table of a split APK),
+    /// the entries of a configuration both tables define are the union of theirs
+    pub(crate) fn merge(&mut self, other: ResourceTable) {
+        for package in other.packages {
+            let Some(base_package) = self.packages.iter_mut().find(|p| p.id == package.id) else {
+                self.packages.push(package);
+                continue;
+            };
+            for resource_type in package.types {
+                let Some(base_type) = base_package
+                    .types
+                    .iter_mut()
+                    .find(|t| t.id == resource_type.id)
+                else {
+                    base_package.types.push(resource_type);
+                    continue;
+                };
+                for config in resource_type.configs {
+                    match base_type
+                        .configs
+                        .iter_mut()
+                        .find(|c| c.config == config.config)
+                    {
+                        Some(base_config) => base_config.entries.extend(config.entries),
+                        None => base_type.configs.push(config),
+                    }
+                }
+            }
+        }
+    }
+
+    fn get_type(&self, id: u32) -> Option<&ResourceType> {
+        let package = self.packages.iter().find(|p| p.id as u32 == id >> 24)?;
+        package
+            .types
+            .iter()
+            .find(|t| t.id as u32 == (id >> 16) & 0xff)
+    }
+
+    /// Values of a resource id in every configuration that defines it
+    pub fn entries(&self, id: u32) -> Vec<(&Config, &Entry)> {
+        let index = (id & 0xffff) as u16;
+        self.get_type(id)
+            .into_iter()
+            .flat_map(|t| &t.configs)
+            .filter_map(|c| Some((&c.config, c.entries.get(&index)?)))
+            .collect()
+    }
+
+    /// Name of a resource id without its package (e.g. `string/app_name`)
+    pub fn name(&self, id: u32) -> Option<String> {
+        let (_, entry) = self.entries(id).into_iter().next()?;
+        Some(format!("{}/{}", self.get_type(id)?.name, entry.key))
+    }
+
+    /// Id of a resource name, with or without its package (e.g. `com.example:string/app_name`)
+    pub fn id(&self, name: &str) -> Option<u32> {
+        let (package, name) = match name.split_once(':') {
+            Some((package, name)) => (Some(package), name),
+            None => (None, name),
+        };
+        let (type_name, key) = name.split_once('/')?;
+        self.packages
+            .iter()
+            .filter(|p| package.is_none_or(|n| n == p.name))
+            .flat_map(|p| p.types.iter().map(move |t| (p, t)))
+            .filter(|(_, t)| t.name == type_name)
+            .find_map(|(p, t)| {
+                t.configs.iter().find_map(|c| {
+                    let (index, _) = c.entries.iter().find(|(_, e)| e.key == key)?;
+                    Some((p.id as u32) << 24 | (t.id as u32) << 16 | *index as u32)
+                })
+            })
+    }
+
+    /// Values of a resource in every configuration, references to other resources are followed.
+    ///
+    /// A reference is resolved in the same configuration when the target defines it,
+    /// otherwise in the default one. Bags (e.g. styles) have no single value and are skipped.
+    pub fn resolve(&self, reference: &ResourceRef) -> Vec<ResolvedValue<'_>> {
+        let id = match reference {
+            ResourceRef::Id(id) => *id,
+            ResourceRef::Name(name) => match self.id(name) {
+                Some(id) => id,
+                None => return Vec::new(),
+            },
+        };
+        self.entries(id)
+            .into_iter()
+            .filter_map(|(config, entry)| match &entry.value {
+                EntryValue::Simple(value) => Some(ResolvedValue {
+                    config,
+                    value: self.follow(value, config, MAX_REFERENCE_DEPTH),
+                }),
+                EntryValue::Complex { .. } => None,
+            })
+            .collect()
+    }
+
+    /// Value of a resource in the default configuration, or the first one defining it
+    pub fn resolve_default(&self, reference: &ResourceRef) -> Option<&ResValue> {
+        let values = self.resolve(reference);
+        values
+            .iter()
+            .find(|v| v.config.is_default())
+            .or(values.first())
+            .map(|v| v.value)
+    }
+
+    fn follow<'a>(&'a self, value: &'a ResValue, config: &Config, depth: usize) -> &'a ResValue {
+        let ResValue::Reference(id) = value else {
+            return value;
+        };
+        if depth == 0 {
+            log::warn!("Reference chain too deep at {id:#010x}");
+            return value;
+        }
+        let entries = self.entries(*id);
+        let target = entries
+            .iter()
+            .find(|(c, _)| *c == config)
+            .or_else(|| entries.iter().find(|(c, _)| c.is_default()))
+            .or(entries.first());
+        match target {
+            Some((
+                _,
+                Entry {
+                    value: EntryValue::Simple(target),
+                    ..
+                },
+            )) => self.follow(target, config, depth - 1),
+            _ => value,
+        }
+    }
+}
+
+impl Package {
+    fn parse(buf: &[u8], chunk: Chunk, strings: &StringPool) -> Result<Self, ResourceError> {
+        let data = chunk.data(buf);
+        let id = read_u32(data, 8)? as u8;
+        let name = (0..128)
+            .map(|i| read_u16(data, 12 + i * 2))
+            .take_while(|c| !matches!(c, Ok(0)))
+            .collect::<Result<Vec<_>, _>>()?;
+        let type_strings_offset = read_u32(data, 268)? as usize;
+        let key_strings_offset = read_u32(data, 276)? as usize;
+
+        let mut type_strings = StringPool::default();
+        let mut key_strings = StringPool::default();
+        let mut types: Vec<ResourceType> = Vec::new();
+        for child in chunk.children(buf) {
+            let child = child?;
+            let relative = child.offset - chunk.offset;
+            match child.chunk_type {
+                RES_STRING_POOL_TYPE if relative == type_strings_offset => {
+                    type_strings = StringPool::parse(buf, child)?
+                }
+                RES_STRING_POOL_TYPE if relative == key_strings_offset => {
+                    key_strings = StringPool::parse(buf, child)?
+                }
+                RES_TABLE_TYPE_TYPE => {
+                    let (type_id, config) = parse_type(buf, child, &key_strings, strings)?;
+                    // Type ids start at 1, the index of the name in the type strings plus one
+                    let Some(name_index) = type_id.checked_sub(1) else {
+                        log::warn!("Type with id 0 at offset {}", child.offset);
+                        continue;
+                    };
+                    match types.iter_mut().find(|t| t.id == type_id) {
+                        Some(resource_type) => resource_type.configs.push(config),
+                        None => types.push(ResourceType {
+                            id: type_id,
+                            name: type_strings
+                                .get(name_index as u32)
+                                .unwrap_or_default()
+                                .to_string(),
+                            configs: vec![config],
+                        }),
+                    }
+                }
+                // Type specs, libraries, overlayables...
+                _ => {}
+            }
+        }
+
+        Ok(Package {
+            id,
+            name: String::from_utf16_lossy(&name),
+            types,
+        })
+    }
+}
+
+/// Parses a `ResTable_type` chunk, the entries of a type for one configuration
+fn parse_type(
+    buf: &[u8],
+    chunk: Chunk,
+    keys: &StringPool,
+    strings: &StringPool,
+) -> Result<(u8, TypeConfig), ResourceError> {
+    let data = chunk.data(buf);
+    let id = read_u8(data, 8)?;
+    let flags = read_u8(data, 9)?;
+    let entry_count = read_u32(data, 12)? as usize;
+    let entries_start = read_u32(data, 16)? as usize;
+    let config = Config::parse(data, 20);
+    let offsets = chunk.header_size;
+
+    let mut entries = BTreeMap::new();
+    for i in 0..entry_count {
+        let (index, offset) = if flags & FLAG_SPARSE != 0 {
+            (
+                read_u16(data, offsets + i * 4)?,
+                read_u16(data, offsets + i * 4 + 2)? as u32 * 4,
+            )
+        } else if flags & FLAG_OFFSET16 != 0 {
+            match read_u16(data, offsets + i * 2)? {
+                0xffff => continue,
+                offset => (i as u16, offset as u32 * 4),
+            }
+        } else {
+            match read_u32(data, offsets + i * 4)? {
+                NO_ENTRY => continue,
+                offset => (i as u16, offset),
+            }
+        };
+        let entry = parse_entry(data, entries_start + offset as usize, keys, strings)?;
+        entries.insert(index, entry);
+    }
+    Ok((id, TypeConfig { config, entries }))
+}
+
+/// Parses a `ResTable_entry` followed by its value or its map
+fn parse_entry(
+    data: &[u8],
+    offset: usize,
+    keys: &StringPool,
+    strings: &StringPool,
+) -> Result<Entry, ResourceError> {
+    let size = read_u16(data, offset)?;
+    let flags = read_u16(data, offset + 2)?;
+    if flags & FLAG_COMPACT != 0 {
+        // The size holds the key and the upper byte of the flags the type of the value
+        return Ok(Entry {
+            key: keys.get(size as u32)?.to_string(),
+            value: EntryValue::Simple(ResValue::decode(
+                (flags >> 8) as u8,
+                read_u32(data, offset + 4)?,
+                strings,
+            )?),
+        });
+    }
+
+    let key = keys.get(read_u32(data, offset + 4)?)?.to_string();
+    let value = if flags & FLAG_COMPLEX != 0 {
+        let parent = read_u32(data, offset + 8)?;
+        let count = read_u32(data, offset + 12)? as usize;
+        let start = offset + size as usize;
+        let items = (0..count)
+            .map(|i| {
+                let item = start + i * 12;
+                Ok((
+                    read_u32(data, item)?,
+                    ResValue::read(data, item + 4, strings)?,
+                ))
+            })
+            .collect::<Result<_, ResourceError>>()?;
+        EntryValue::Complex { parent, items }
+    } else {
+        EntryValue::Simple(ResValue::read(data, offset + size as usize, strings)?)
+    };
+    Ok(Entry { key, value })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        chunk::{Chunk, RES_TABLE_PACKAGE_TYPE, RES_TABLE_TYPE_TYPE},
+        config::Config,
+        value::ResValue,
+        EntryValue, ResourceTable,
+    };
+    use crate::manifest::value::ResourceRef;
+    use std::{fs::File, io::Read};
+
+    fn table() -> ResourceTable {
+        let mut buf = Vec::new();
+        File::open("tests/resources/a.arsc")
+            .unwrap()
+            .read_to_end(&mut buf)
+            .unwrap();
+        ResourceTable::parse(&buf).unwrap()
+    }
+
+    #[test]
+    fn test_parse() {
+        let table = table();
+        assert_eq!(table.packages.len(), 1);
+        let package = &table.packages[0];
+        assert_eq!(package.id, 0x7f);
+        assert_eq!(package.name, "com.example.rich");
+        let names: Vec<_> = package.types.iter().map(|t| t.name.as_str()).collect();
+        assert_eq!(
+            names,
+            vec!["mipmap", "dimen", "color", "bool", "style", "string", "xml"]
+        );
+
+        assert_eq!(table.name(0x7f100000), Some("string/app_name".into()));
+        assert_eq!(table.id("string/app_name"), Some(0x7f100000));
+        assert_eq!(
+            table.id("com.example.rich:xml/file_paths"),
+            Some(0x7f150000)
+        );
+        assert_eq!(table.id("com.other:xml/file_paths"), None);
+        assert_eq!(table.name(0x7f100002), None);
+
+        let value = |id| match &table.entries(id)[0].1.value {
+            EntryValue::Simple(value) => value.clone(),
+            other => panic!("Unexpected {other:?}"),
+        };
+        assert_eq!(value(0x7f060000), ResValue::Dimension("16.0dip".into()));
+        assert_eq!(value(0x7f070000), ResValue::Color(0xff112233));
+        assert_eq!(value(0x7f080000), ResValue::Boolean(false));
+        assert_eq!(
+            table.entries(0x7f0a0000)[0].1.value,
+            EntryValue::Complex {
+                parent: 0x01030005,
+                items: vec![(0x01010098, ResValue::Reference(0x7f070000))],
+            }
+        );
+        let (config, _) = table.entries(0x7f030000)[0];
+        assert_eq!(config.to_string(), "hdpi-v4");
+    }
+
+    #[test]
+    fn test_resolve() {
+        let table = table();
+        let values: Vec<_> = table
+            .resolve(&ResourceRef::Id(0x7f100000))
+            .iter()
+            .map(|v| (v.config.to_string(), v.value.clone()))
+            .collect();
+        assert_eq!(
+            values,
+            vec![
+                ("default".into(), ResValue::String("Rich App".into())),
+                ("fr".into(), ResValue::String("Appli Riche".into())),
+            ]
+        );
+        // string/app_alias references string/app_name
+        assert_eq!(
+            table.resolve_default(&ResourceRef::Name("string/app_alias".into())),
+            Some(&ResValue::String("Rich App".into()))
+        );
+        assert_eq!(
+            table.resolve_default(&ResourceRef::Id(0x7f150001)),
+            Some(&ResValue::String(
+                "res/xml/network_security_config.xml".into()
+            ))
+        );
+        assert!(table.resolve(&ResourceRef::Id(0x7f0a0000)).is_empty());
+        assert!(Config::default().is_default());
+    }
+
+    #[test]
+    fn test_merge() {
+        // A base without the `xml` type nor the French strings
+        let mut base = table();
+        let package = &mut base.packages[0];
+        package.types.retain(|t| t.name != "xml");
+        let strings = package
+            .types
+            .iter_mut()
+            .find(|t| t.name == "string")
+            .unwrap();
+        strings.configs.retain(|c| c.config.is_default());
+        assert!(base.id("xml/file_paths").is_none());
+
+        base.merge(table());
+        assert_eq!(base.packages.len(), 1);
+        assert_eq!(base.id("xml/file_paths"), Some(0x7f150000));
+        let configs: Vec<_> = base
+            .resolve(&ResourceRef::Id(0x7f100000))
+            .iter()
+            .map(|v| v.config.to_string())
+            .collect();
+        assert_eq!(configs, ["default", "fr"]);
+    }
+
+    #[test]
+    fn test_malformed() {
+        assert!(ResourceTable::parse(&[0x02, 0x00, 0x0c, 0x00, 0xff, 0, 0, 0]).is_err());
+        assert!(ResourceTable::parse(&[]).is_err());
+    }
+
+    #[test]
+    fn test_type_id_0() {
+        let mut buf = Vec::new();
+        File::open("tests/resources/a.arsc")
+            .unwrap()
+            .read_to_end(&mut buf)
+            .unwrap();
+        let package = Chunk::read(&buf, 0)
+            .unwrap()
+            .children(&buf)
+            .map(Result::unwrap)
+            .find(|c| c.chunk_type == RES_TABLE_PACKAGE_TYPE)
+            .unwrap();
+        let first_type = package
+            .children(&buf)
+            .map(Result::unwrap)
+            .find(|c| c.chunk_type == RES_TABLE_TYPE_TYPE)
+            .unwrap();
+        let type_id = buf[first_type.offset + 8];
+        // Every configuration of the type
+        for offset in package
+            .children(&buf)
+            .map(Result::unwrap)
+            .filter(|c| c.chunk_type == RES_TABLE_TYPE_TYPE && buf[c.offset + 8] == type_id)
+            .map(|c| c.offset)
+            .collect::<Vec<_>>()
+        {
+            buf[offset + 8] = 0;
+        }
+
+        let table = ResourceTable::parse(&buf).unwrap();
+        let types = &table.packages[0].types;
+        assert!(!types.is_empty());
+        assert!(types.iter().all(|t| t.id != 0 && t.id != type_id));
+    }
+}
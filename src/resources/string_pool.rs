@@ -0,0 +1,77 @@
+use super::{
+    chunk::{read_u16, read_u32, read_u8, Chunk, RES_STRING_POOL_TYPE},
+    errors::ResourceError,
+};
+
+const UTF8_FLAG: u32 = 0x100;
+
+/// `ResStringPool`, strings are decoded eagerly and styles are ignored
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct StringPool {
+    strings: Vec<String>,
+}
+
+impl StringPool {
+    pub(crate) fn parse(buf: &[u8], chunk: Chunk) -> Result<Self, ResourceError> {
+        let chunk = chunk.expect(RES_STRING_POOL_TYPE)?;
+        let data = chunk.data(buf);
+        let count = read_u32(data, 8)? as usize;
+        let utf8 = read_u32(data, 16)? & UTF8_FLAG != 0;
+        let strings_start = read_u32(data, 20)? as usize;
+        let offsets_start = chunk.header_size;
+
+        let mut strings = Vec::with_capacity(count.min(data.len() / 4));
+        for i in 0..count {
+            let offset = strings_start + read_u32(data, offsets_start + i * 4)? as usize;
+            strings.push(if utf8 {
+                read_utf8(data, offset)?
+            } else {
+                read_utf16(data, offset)?
+            });
+        }
+        Ok(StringPool { strings })
+    }
+
+    pub fn get(&self, index: u32) -> Result<&str, ResourceError> {
+        self.strings
+            .get(index as usize)
+            .map(String::as_str)
+            .ok_or(ResourceError::StringIndex(index))
+    }
+}
+
+/// Length of a UTF-8 string, one byte or two if the high bit is set
+fn read_utf8_len(data: &[u8], offset: usize) -> Result<(usize, usize), ResourceError> {
+    let first = read_u8(data, offset)? as usize;
+    if first & 0x80 != 0 {
+        let second = read_u8(data, offset + 1)? as usize;
+        Ok((((first & 0x7f) << 8) | second, 2))
+    } else {
+        Ok((first, 1))
+    }
+}
+
+fn read_utf8(data: &[u8], offset: usize) -> Result<String, ResourceError> {
+    // Length in UTF-16 code units, then in bytes
+    let (_, skip) = read_utf8_len(data, offset)?;
+    let (len, skip2) = read_utf8_len(data, offset + skip)?;
+    let start = offset + skip + skip2;
+    let bytes = data
+        .get(start..start + len)
+        .ok_or(ResourceError::Eof(start + len))?;
+    Ok(String::from_utf8_lossy(bytes).into_owned())
+}
+
+fn read_utf16(data: &[u8], offset: usize) -> Result<String, ResourceError> {
+    let first = read_u16(data, offset)? as usize;
+    let (len, start) = if first & 0x8000 != 0 {
+        let second = read_u16(data, offset + 2)? as usize;
+        (((first & 0x7fff) << 16) | second, offset + 4)
+    } else {
+        (first, offset + 2)
+    };
+    let units = (0..len)
+        .map(|i| read_u16(data, start + i * 2))
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(String::from_utf16_lossy(&units))
+}
@@ -0,0 +1,113 @@
+use serde::Serialize;
+
+use super::{
+    chunk::{read_u32, read_u8},
+    errors::ResourceError,
+    string_pool::StringPool,
+};
+
+/// `Res_value`, strings are looked up in the global string pool of the table
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub enum ResValue {
+    Null,
+    /// Id of another resource, see `ResourceTable::resolve`
+    Reference(u32),
+    /// Id of a theme attribute
+    Attribute(u32),
+    /// Strings, but also the paths of file resources (e.g. `res/xml/file_paths.xml`)
+    String(String),
+    Float(f32),
+    /// Dimension with its unit, e.g. `16.0dip`
+    Dimension(String),
+    /// Fraction with its unit, e.g. `50.0%` or `50.0%p`
+    Fraction(String),
+    Integer(i32),
+    Boolean(bool),
+    /// ARGB color
+    Color(u32),
+    Other {
+        data_type: u8,
+        data: u32,
+    },
+}
+
+const RADIX_MULTS: [f32; 4] = [
+    1.0 / (1 << 8) as f32,
+    1.0 / (1 << 15) as f32,
+    1.0 / (1 << 23) as f32,
+    1.0 / (1u32 << 31) as f32,
+];
+
+/// Value of a complex (dimension or fraction) unit
+fn complex_to_float(data: u32) -> f32 {
+    (data & 0xffffff00) as i32 as f32 * RADIX_MULTS[((data >> 4) & 0x3) as usize]
+}
+
+impl ResValue {
+    /// Decodes a value from its type and data
+    pub(crate) fn decode(
+        data_type: u8,
+        data: u32,
+        strings: &StringPool,
+    ) -> Result<Self, ResourceError> {
+        Ok(match data_type {
+            0x00 => ResValue::Null,
+            0x01 | 0x07 => ResValue::Reference(data),
+            0x02 | 0x08 => ResValue::Attribute(data),
+            0x03 => ResValue::String(strings.get(data)?.to_string()),
+            0x04 => ResValue::Float(f32::from_bits(data)),
+            0x05 => {
+                let unit = match data & 0xf {
+                    0 => "px",
+                    1 => "dip",
+                    2 => "sp",
+                    3 => "pt",
+                    4 => "in",
+                    5 => "mm",
+                    _ => "",
+                };
+                ResValue::Dimension(format!("{:?}{unit}", complex_to_float(data)))
+            }
+            0x06 => {
+                let unit = if data & 0xf == 1 { "%p" } else { "%" };
+                ResValue::Fraction(format!("{:?}{unit}", complex_to_float(data) * 100.0))
+            }
+            0x10 | 0x11 => ResValue::Integer(data as i32),
+            0x12 => ResValue::Boolean(data != 0),
+            0x1c..=0x1f => ResValue::Color(data),
+            data_type => ResValue::Other { data_type, data },
+        })
+    }
+
+    /// Reads a `Res_value` (size, padding, type and data)
+    pub(crate) fn read(
+        data: &[u8],
+        offset: usize,
+        strings: &StringPool,
+    ) -> Result<Self, ResourceError> {
+        ResValue::decode(
+            read_u8(data, offset + 3)?,
+            read_u32(data, offset + 4)?,
+            strings,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ResValue, StringPool};
+
+    #[test]
+    fn test_decode() {
+        let strings = StringPool::default();
+        let decode = |data_type, data| ResValue::decode(data_type, data, &strings).unwrap();
+        assert_eq!(decode(0x05, 0x1001), ResValue::Dimension("16.0dip".into()));
+        assert_eq!(decode(0x05, 0x1802), ResValue::Dimension("24.0sp".into()));
+        assert_eq!(decode(0x06, 0x40000030), ResValue::Fraction("50.0%".into()));
+        assert_eq!(decode(0x12, 0xffffffff), ResValue::Boolean(true));
+        assert_eq!(decode(0x11, 0xffffffff), ResValue::Integer(-1));
+        assert_eq!(decode(0x1c, 0xff112233), ResValue::Color(0xff112233));
+        assert_eq!(decode(0x01, 0x7f100000), ResValue::Reference(0x7f100000));
+        assert!(ResValue::decode(0x03, 0, &strings).is_err());
+    }
+}
@@ -0,0 +1,463 @@
+//! The APK Signing Block between the ZIP entries and the central directory, holding the
+//! v2, v3 and v3.1 scheme blocks, see
+//! <https://source.android.com/docs/security/features/apksigning/v2#apk-signing-block>
+
+use std::io::{Read, Seek, SeekFrom};
+
+use super::{
+    certificate::Certificate,
+    crypto::{self, Hash, Padding},
+    errors::SigningError,
+    LineageNode, Signer, Verdict,
+};
+
+const MAGIC: &[u8; 16] = b"APK Sig Block 42";
+const EOCD_SIGNATURE: u32 = 0x06054b50;
+const EOCD_SIZE: usize = 22;
+
+pub(crate) const V2_ID: u32 = 0x7109871a;
+pub(crate) const V3_ID: u32 = 0xf05368c0;
+pub(crate) const V31_ID: u32 = 0x1b93ad61;
+/// v3 signed data attribute holding the signing certificate lineage
+const PROOF_OF_ROTATION_ID: u32 = 0x3ba06f8c;
+
+/// Little-endian reader of the length-prefixed values the signing block is made of
+#[derive(Debug, Clone)]
+struct Reader<'a> {
+    buf: &'a [u8],
+    offset: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn new(buf: &'a [u8]) -> Self {
+        Reader { buf, offset: 0 }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.offset >= self.buf.len()
+    }
+
+    fn bytes(&mut self, len: usize) -> Result<&'a [u8], SigningError> {
+        let bytes = self
+            .buf
+            .get(self.offset..self.offset.saturating_add(len))
+            .ok_or(SigningError::Eof(self.offset))?;
+        self.offset += len;
+        Ok(bytes)
+    }
+
+    fn u32(&mut self) -> Result<u32, SigningError> {
+        let b = self.bytes(4)?;
+        Ok(u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+    }
+
+    fn u64(&mut self) -> Result<u64, SigningError> {
+        let b = self.bytes(8)?;
+        Ok(u64::from_le_bytes(b.try_into().unwrap()))
+    }
+
+    /// Reader over the next value, prefixed by its 32-bit length
+    fn prefixed(&mut self) -> Result<Reader<'a>, SigningError> {
+        let len = self.u32()? as usize;
+        self.bytes(len).map(Reader::new)
+    }
+
+    fn rest(&self) -> &'a [u8] {
+        &self.buf[self.offset.min(self.buf.len())..]
+    }
+}
+
+/// Offsets of the ZIP sections the signing block sits between
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct ZipSections {
+    pub central_directory_offset: u64,
+    pub central_directory_size: u64,
+    pub eocd_offset: u64,
+}
+
+/// Locates the end of central directory record, the last one whose comment ends the file
+pub(crate) fn find_sections<R: Read + Seek>(reader: &mut R) -> Result<ZipSections, SigningError> {
+    let len = reader.seek(SeekFrom::End(0))?;
+    let tail_len = len.min((EOCD_SIZE + u16::MAX as usize) as u64);
+    reader.seek(SeekFrom::Start(len - tail_len))?;
+    let mut tail = vec![0; tail_len as usize];
+    reader.read_exact(&mut tail)?;
+
+    for i in (0..=tail.len().saturating_sub(EOCD_SIZE)).rev() {
+        let mut eocd = Reader::new(&tail[i..]);
+        if eocd.u32().ok() != Some(EOCD_SIGNATURE) {
+            continue;
+        }
+        eocd.bytes(8)?;
+        let central_directory_size = eocd.u32()? as u64;
+        let central_directory_offset = eocd.u32()? as u64;
+        let comment_len = eocd.bytes(2).map(|b| u16::from_le_bytes([b[0], b[1]]))?;
+        if comment_len as usize == tail.len() - i - EOCD_SIZE {
+            return Ok(ZipSections {
+                central_directory_offset,
+                central_directory_size,
+                eocd_offset: len - tail_len + i as u64,
+            });
+        }
+    }
+    Err(SigningError::NoEndOfCentralDirectory)
+}
+
+/// ID-value pairs of the signing block
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct SigningBlock {
+    /// Offset of the block in the archive, where the ZIP entries end
+    pub offset: u64,
+    pub pairs: Vec<(u32, Vec<u8>)>,
+}
+
+/// Reads the signing block preceding the central directory, if the APK has one
+pub(crate) fn read_block<R: Read + Seek>(
+    reader: &mut R,
+    sections: &ZipSections,
+) -> Result<Option<SigningBlock>, SigningError> {
+    let end = sections.central_directory_offset;
+    if end < 32 {
+        return Ok(None);
+    }
+    // Size of the block (excluding this field) and the magic
+    let mut footer = [0; 24];
+    reader.seek(SeekFrom::Start(end - 24))?;
+    reader.read_exact(&mut footer)?;
+    if &footer[8..] != MAGIC {
+        return Ok(None);
+    }
+    let size = Reader::new(&footer).u64()?;
+    if size < 24 || size > end - 8 {
+        return Err(SigningError::MalformedBlock("Invalid block size"));
+    }
+
+    let offset = end - size - 8;
+    let mut buf = vec![0; size as usize + 8];
+    reader.seek(SeekFrom::Start(offset))?;
+    reader.read_exact(&mut buf)?;
+    if Reader::new(&buf).u64()? != size {
+        return Err(SigningError::MalformedBlock("Block sizes differ"));
+    }
+
+    let mut pairs = Vec::new();
+    let mut reader = Reader::new(&buf[8..buf.len() - 24]);
+    while !reader.is_empty() {
+        let len = reader.u64()?;
+        if len < 4 {
+            return Err(SigningError::MalformedBlock("Invalid pair size"));
+        }
+        let id = reader.u32()?;
+        pairs.push((id, reader.bytes(len as usize - 4)?.to_vec()));
+    }
+    Ok(Some(SigningBlock { offset, pairs }))
+}
+
+/// A signature algorithm of the v2 and v3 schemes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Algorithm {
+    id: u32,
+    hash: Hash,
+    padding: Padding,
+}
+
+impl Algorithm {
+    /// The algorithms with a chunked content digest, the verity ones (`0x0421`, `0x0423` and
+    /// `0x0425`) digest a Merkle tree instead and are not supported
+    fn from_id(id: u32) -> Option<Self> {
+        let (hash, padding) = match id {
+            0x0101 => (Hash::Sha256, Padding::Pss),
+            0x0102 => (Hash::Sha512, Padding::Pss),
+            0x0103 | 0x0201 | 0x0301 => (Hash::Sha256, Padding::Pkcs1),
+            0x0104 | 0x0202 => (Hash::Sha512, Padding::Pkcs1),
+            _ => return None,
+        };
+        Some(Algorithm { id, hash, padding })
+    }
+}
+
+/// A node of the proof-of-rotation lineage
+struct RawLineageNode<'a> {
+    /// Certificate and `parent_algorithm`, what `signature` is computed over
+    signed_data: &'a [u8],
+    certificate: &'a [u8],
+    /// Algorithm of the previous node's signature
+    parent_algorithm: u32,
+    flags: u32,
+    /// Algorithm the certificate signs the next node with
+    algorithm: u32,
+    signature: &'a [u8],
+}
+
+/// A signer as encoded in a v2, v3 or v3.1 scheme block
+struct RawSigner<'a> {
+    signed_data: &'a [u8],
+    /// `(algorithm, digest)` of the archive content
+    digests: Vec<(u32, &'a [u8])>,
+    certificates: Vec<&'a [u8]>,
+    /// SDK range of the signed data, v3 only
+    sdk: Option<(u32, u32)>,
+    /// SDK range outside of the signed data, which has to match it
+    unsigned_sdk: Option<(u32, u32)>,
+    lineage: Vec<RawLineageNode<'a>>,
+    /// `(algorithm, signature)` of the signed data
+    signatures: Vec<(u32, &'a [u8])>,
+    /// DER encoded `SubjectPublicKeyInfo`
+    public_key: &'a [u8],
+}
+
+/// Sequence of length-prefixed `(ID, length-prefixed value)` pairs
+fn id_values<'a>(mut reader: Reader<'a>) -> Result<Vec<(u32, &'a [u8])>, SigningError> {
+    let mut values = Vec::new();
+    while !reader.is_empty() {
+        let mut pair = reader.prefixed()?;
+        values.push((pair.u32()?, pair.prefixed()?.rest()));
+    }
+    Ok(values)
+}
+
+fn lineage(mut reader: Reader) -> Result<Vec<RawLineageNode>, SigningError> {
+    // Version
+    reader.u32()?;
+    let mut lineage = Vec::new();
+    while !reader.is_empty() {
+        let mut node = reader.prefixed()?;
+        let mut signed_data = node.prefixed()?;
+        lineage.push(RawLineageNode {
+            signed_data: signed_data.rest(),
+            certificate: signed_data.prefixed()?.rest(),
+            parent_algorithm: signed_data.u32()?,
+            flags: node.u32()?,
+            algorithm: node.u32()?,
+            signature: node.prefixed()?.rest(),
+        });
+    }
+    Ok(lineage)
+}
+
+fn decode(id: u32, value: &[u8]) -> Result<Vec<RawSigner<'_>>, SigningError> {
+    let mut signers = Vec::new();
+    let mut reader = Reader::new(value).prefixed()?;
+    while !reader.is_empty() {
+        let mut signer = reader.prefixed()?;
+        let mut signed_data = signer.prefixed()?;
+        let raw = signed_data.rest();
+        let digests = id_values(signed_data.prefixed()?)?;
+        let mut certificates = Vec::new();
+        let mut reader = signed_data.prefixed()?;
+        while !reader.is_empty() {
+            certificates.push(reader.prefixed()?.rest());
+        }
+        let v3 = id != V2_ID;
+        let sdk = match v3 {
+            true => Some((signed_data.u32()?, signed_data.u32()?)),
+            false => None,
+        };
+        let mut lineage = Vec::new();
+        let mut attributes = signed_data.prefixed()?;
+        while !attributes.is_empty() {
+            let mut attribute = attributes.prefixed()?;
+            if attribute.u32()? == PROOF_OF_ROTATION_ID {
+                lineage = self::lineage(attribute)?;
+            }
+        }
+        let unsigned_sdk = match v3 {
+            true => Some((signer.u32()?, signer.u32()?)),
+            false => None,
+        };
+        signers.push(RawSigner {
+            signed_data: raw,
+            digests,
+            certificates,
+            sdk,
+            unsigned_sdk,
+            lineage,
+            signatures: id_values(signer.prefixed()?)?,
+            public_key: signer.prefixed()?.rest(),
+        });
+    }
+    Ok(signers)
+}
+
+/// Decodes the signers of a v2, v3 or v3.1 scheme block
+pub(crate) fn signers(id: u32, value: &[u8]) -> Result<Vec<Signer>, SigningError> {
+    decode(id, value)?
+        .into_iter()
+        .map(|signer| {
+            Ok(Signer {
+                path: None,
+                certificates: signer
+                    .certificates
+                    .iter()
+                    .map(|c| Certificate::parse(c))
+                    .collect::<Result<_, _>>()?,
+                min_sdk: signer.sdk.map(|(min, _)| min),
+                max_sdk: signer.sdk.map(|(_, max)| max),
+                lineage: signer
+                    .lineage
+                    .iter()
+                    .map(|node| {
+                        Ok(LineageNode {
+                            certificate: Certificate::parse(node.certificate)?,
+                            flags: node.flags,
+                        })
+                    })
+                    .collect::<Result<_, SigningError>>()?,
+            })
+        })
+        .collect()
+}
+
+/// Chunked digest of the archive with the signing block left out, see
+/// <https://source.android.com/docs/security/features/apksigning/v2#integrity-protected-contents>
+pub(crate) fn content_digest<R: Read + Seek>(
+    reader: &mut R,
+    sections: &ZipSections,
+    block_offset: u64,
+    hash: Hash,
+) -> Result<Vec<u8>, SigningError> {
+    const CHUNK_SIZE: u64 = 1 << 20;
+
+    // The end of central directory, pointing at where the signing block starts
+    let mut eocd = Vec::new();
+    reader.seek(SeekFrom::Start(sections.eocd_offset))?;
+    reader.read_to_end(&mut eocd)?;
+    eocd[16..20].copy_from_slice(&(block_offset as u32).to_le_bytes());
+
+    let mut chunk_digests = Vec::new();
+    let mut count: u32 = 0;
+    let mut chunk = Vec::new();
+    let mut digest_chunk = |chunk: &[u8]| {
+        chunk_digests.extend(hash.digest(&[&[0xa5], &(chunk.len() as u32).to_le_bytes(), chunk]));
+        count += 1;
+    };
+    for (offset, len) in [
+        (0, block_offset),
+        (
+            sections.central_directory_offset,
+            sections.central_directory_size,
+        ),
+    ] {
+        reader.seek(SeekFrom::Start(offset))?;
+        let mut remaining = len;
+        while remaining > 0 {
+            chunk.resize(remaining.min(CHUNK_SIZE) as usize, 0);
+            reader.read_exact(&mut chunk)?;
+            digest_chunk(&chunk);
+            remaining -= chunk.len() as u64;
+        }
+    }
+    for chunk in eocd.chunks(CHUNK_SIZE as usize) {
+        digest_chunk(chunk);
+    }
+
+    Ok(hash.digest(&[&[0x5a], &count.to_le_bytes(), &chunk_digests]))
+}
+
+fn failed(message: impl Into<String>) -> Verdict {
+    Verdict::Failed(message.into())
+}
+
+/// Checks the signatures of the certificate lineage, each node is signed by the previous one
+fn verify_lineage(signer: &RawSigner) -> Result<(), Verdict> {
+    for (previous, node) in signer.lineage.iter().zip(signer.lineage.iter().skip(1)) {
+        if node.parent_algorithm != previous.algorithm {
+            return Err(failed("Lineage signature algorithms differ"));
+        }
+        let algorithm = Algorithm::from_id(previous.algorithm).ok_or_else(|| {
+            Verdict::Unsupported(format!(
+                "Unsupported lineage signature algorithm {:#06x}",
+                previous.algorithm
+            ))
+        })?;
+        let (_, certificate) = x509_parser::parse_x509_certificate(previous.certificate)
+            .map_err(|_| failed("Malformed lineage certificate"))?;
+        crypto::verify(
+            certificate.public_key().raw,
+            algorithm.hash,
+            algorithm.padding,
+            node.signed_data,
+            node.signature,
+        )
+        .map_err(|_| failed("Invalid lineage signature"))?;
+    }
+    match (signer.lineage.last(), signer.certificates.first()) {
+        (Some(last), Some(certificate)) if last.certificate != *certificate => {
+            Err(failed("Lineage does not end with the signing certificate"))
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Verifies a signer, `digest` computes the content digest for a hash
+fn verify_signer(
+    signer: &RawSigner,
+    digest: &mut dyn FnMut(Hash) -> Result<Vec<u8>, SigningError>,
+) -> Result<(), Verdict> {
+    // The strongest supported algorithm, like the platform does
+    let (algorithm, signature) = signer
+        .signatures
+        .iter()
+        .filter_map(|(id, signature)| Some((Algorithm::from_id(*id)?, *signature)))
+        .max_by_key(|(algorithm, _)| (algorithm.hash, algorithm.padding == Padding::Pss))
+        .ok_or_else(|| Verdict::Unsupported("No supported signature algorithm".to_string()))?;
+    crypto::verify(
+        signer.public_key,
+        algorithm.hash,
+        algorithm.padding,
+        signer.signed_data,
+        signature,
+    )?;
+
+    let signed: Vec<_> = signer.digests.iter().map(|(id, _)| *id).collect();
+    let signatures: Vec<_> = signer.signatures.iter().map(|(id, _)| *id).collect();
+    if signed != signatures {
+        return Err(failed("Digest and signature algorithms differ"));
+    }
+    let certificate = signer
+        .certificates
+        .first()
+        .ok_or_else(|| failed("No certificate"))?;
+    let (_, certificate) = x509_parser::parse_x509_certificate(certificate)
+        .map_err(|_| failed("Malformed certificate"))?;
+    if certificate.public_key().raw != signer.public_key {
+        return Err(failed("Public key does not match the certificate"));
+    }
+    if signer.sdk != signer.unsigned_sdk {
+        return Err(failed("SDK versions differ from the signed ones"));
+    }
+    verify_lineage(signer)?;
+
+    let expected = signer
+        .digests
+        .iter()
+        .find(|(id, _)| *id == algorithm.id)
+        .map(|(_, digest)| *digest);
+    let actual = digest(algorithm.hash).map_err(|e| failed(e.to_string()))?;
+    if expected != Some(actual.as_slice()) {
+        return Err(failed(
+            "Content digest does not match, the APK was modified",
+        ));
+    }
+    Ok(())
+}
+
+/// Verifies every signer of a v2, v3 or v3.1 scheme block
+pub(crate) fn verify(
+    id: u32,
+    value: &[u8],
+    digest: &mut dyn FnMut(Hash) -> Result<Vec<u8>, SigningError>,
+) -> Verdict {
+    let signers = match decode(id, value) {
+        Ok(signers) => signers,
+        Err(e) => return failed(e.to_string()),
+    };
+    if signers.is_empty() {
+        return failed("No signers");
+    }
+    signers
+        .iter()
+        .map(|signer| verify_signer(signer, digest))
+        .find_map(Result::err)
+        .unwrap_or(Verdict::Verified)
+}
@@ -0,0 +1,58 @@
+use serde::Serialize;
+use sha1::{Digest, Sha1};
+use sha2::Sha256;
+use x509_parser::{certificate::X509Certificate, prelude::FromDer, public_key::PublicKey};
+
+use super::{errors::SigningError, hex};
+
+/// Summary of an X.509 signing certificate
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Certificate {
+    /// Distinguished name, e.g. `C=US, O=Example, CN=Example`
+    pub subject: String,
+    pub issuer: String,
+    /// Serial number in hex
+    pub serial_number: String,
+    /// Start of the validity period, in seconds since the Unix epoch
+    pub not_before: i64,
+    /// End of the validity period, in seconds since the Unix epoch
+    pub not_after: i64,
+    /// `RSA`, `EC` or `DSA`, the OID of the algorithm otherwise
+    pub key_algorithm: String,
+    /// Key size in bits, 0 if unknown
+    pub key_size: usize,
+    /// Lowercase hex SHA-1 digest of the DER encoding
+    pub sha1: String,
+    /// Lowercase hex SHA-256 digest of the DER encoding, what Android compares signers by
+    pub sha256: String,
+    /// DER encoding
+    #[serde(skip)]
+    pub der: Vec<u8>,
+}
+
+impl Certificate {
+    pub(crate) fn parse(der: &[u8]) -> Result<Self, SigningError> {
+        let (_, cert) =
+            X509Certificate::from_der(der).map_err(|e| SigningError::Certificate(e.to_string()))?;
+        let key = cert.public_key();
+        let parsed = key.parsed();
+        let key_algorithm = match &parsed {
+            Ok(PublicKey::RSA(_)) => "RSA".to_string(),
+            Ok(PublicKey::EC(_)) => "EC".to_string(),
+            Ok(PublicKey::DSA(_)) => "DSA".to_string(),
+            _ => key.algorithm.algorithm.to_id_string(),
+        };
+        Ok(Certificate {
+            subject: cert.subject().to_string(),
+            issuer: cert.issuer().to_string(),
+            serial_number: cert.serial.to_str_radix(16),
+            not_before: cert.validity().not_before.timestamp(),
+            not_after: cert.validity().not_after.timestamp(),
+            key_algorithm,
+            key_size: parsed.map(|k| k.key_size()).unwrap_or_default(),
+            sha1: hex(&Sha1::digest(der)),
+            sha256: hex(&Sha256::digest(der)),
+            der: der.to_vec(),
+        })
+    }
+}
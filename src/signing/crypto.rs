@@ -0,0 +1,137 @@
+//! Digests and signature checks over the public keys embedded in the signatures
+
+use p256::ecdsa::signature::hazmat::PrehashVerifier;
+use rsa::{BigUint, Pkcs1v15Sign, Pss, RsaPublicKey};
+use sha1::Sha1;
+use sha2::{Digest, Sha256, Sha384, Sha512};
+use x509_parser::{prelude::FromDer, public_key::PublicKey, x509::SubjectPublicKeyInfo};
+
+use super::Verdict;
+
+/// `prime256v1`
+const P256_OID: &str = "1.2.840.10045.3.1.7";
+/// `secp384r1`
+const P384_OID: &str = "1.3.132.0.34";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub(crate) enum Hash {
+    Sha1,
+    Sha256,
+    Sha384,
+    Sha512,
+}
+
+fn digest_with<D: Digest>(parts: &[&[u8]]) -> Vec<u8> {
+    let mut digest = D::new();
+    for part in parts {
+        digest.update(part);
+    }
+    digest.finalize().to_vec()
+}
+
+impl Hash {
+    /// Digest of the concatenation of `parts`
+    pub fn digest(self, parts: &[&[u8]]) -> Vec<u8> {
+        match self {
+            Hash::Sha1 => digest_with::<Sha1>(parts),
+            Hash::Sha256 => digest_with::<Sha256>(parts),
+            Hash::Sha384 => digest_with::<Sha384>(parts),
+            Hash::Sha512 => digest_with::<Sha512>(parts),
+        }
+    }
+
+    fn pkcs1(self) -> Pkcs1v15Sign {
+        match self {
+            Hash::Sha1 => Pkcs1v15Sign::new::<Sha1>(),
+            Hash::Sha256 => Pkcs1v15Sign::new::<Sha256>(),
+            Hash::Sha384 => Pkcs1v15Sign::new::<Sha384>(),
+            Hash::Sha512 => Pkcs1v15Sign::new::<Sha512>(),
+        }
+    }
+
+    fn pss(self) -> Pss {
+        match self {
+            Hash::Sha1 => Pss::new::<Sha1>(),
+            Hash::Sha256 => Pss::new::<Sha256>(),
+            Hash::Sha384 => Pss::new::<Sha384>(),
+            Hash::Sha512 => Pss::new::<Sha512>(),
+        }
+    }
+}
+
+/// Padding of RSA signatures, EC signatures are DER encoded ECDSA ones whatever the padding
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Padding {
+    Pkcs1,
+    Pss,
+}
+
+fn failed(message: &str) -> Verdict {
+    Verdict::Failed(message.to_string())
+}
+
+/// Checks `signature` over the digest of `data` with a DER encoded `SubjectPublicKeyInfo`
+pub(crate) fn verify(
+    public_key: &[u8],
+    hash: Hash,
+    padding: Padding,
+    data: &[u8],
+    signature: &[u8],
+) -> Result<(), Verdict> {
+    let (_, info) =
+        SubjectPublicKeyInfo::from_der(public_key).map_err(|_| failed("Malformed public key"))?;
+    let digest = hash.digest(&[data]);
+    match info.parsed() {
+        Ok(PublicKey::RSA(key)) => {
+            let key = RsaPublicKey::new_with_max_size(
+                BigUint::from_bytes_be(key.modulus),
+                BigUint::from_bytes_be(key.exponent),
+                16384,
+            )
+            .map_err(|_| failed("Invalid RSA public key"))?;
+            let result = match padding {
+                Padding::Pkcs1 => key.verify(hash.pkcs1(), &digest, signature),
+                Padding::Pss => key.verify(hash.pss(), &digest, signature),
+            };
+            result.map_err(|_| failed("Signature does not match the public key"))
+        }
+        Ok(PublicKey::EC(point)) => {
+            let curve = info
+                .algorithm
+                .parameters
+                .as_ref()
+                .and_then(|p| p.as_oid().ok())
+                .map(|oid| oid.to_id_string());
+            let result = match curve.as_deref() {
+                Some(P256_OID) => p256::ecdsa::VerifyingKey::from_sec1_bytes(point.data())
+                    .and_then(|key| {
+                        key.verify_prehash(
+                            &digest,
+                            &p256::ecdsa::DerSignature::from_bytes(signature)?,
+                        )
+                    }),
+                Some(P384_OID) => p384::ecdsa::VerifyingKey::from_sec1_bytes(point.data())
+                    .and_then(|key| {
+                        key.verify_prehash(
+                            &digest,
+                            &p384::ecdsa::DerSignature::from_bytes(signature)?,
+                        )
+                    }),
+                _ => {
+                    return Err(Verdict::Unsupported(format!(
+                        "Unsupported elliptic curve {}",
+                        curve.unwrap_or_default()
+                    )))
+                }
+            };
+            result.map_err(|_| failed("Signature does not match the public key"))
+        }
+        Ok(PublicKey::DSA(_)) => Err(Verdict::Unsupported(
+            "DSA signatures are not supported".to_string(),
+        )),
+        _ => Err(Verdict::Unsupported(format!(
+            "Unsupported key algorithm {}",
+            info.algorithm.algorithm.to_id_string()
+        ))),
+    }
+}
@@ -0,0 +1,131 @@
+//! Minimal DER reader, enough to walk the PKCS #7 `SignedData` of v1 signature block files
+
+use super::errors::SigningError;
+
+pub(crate) const INTEGER: u8 = 0x02;
+pub(crate) const OCTET_STRING: u8 = 0x04;
+pub(crate) const OID: u8 = 0x06;
+pub(crate) const SEQUENCE: u8 = 0x30;
+pub(crate) const SET: u8 = 0x31;
+/// `[0]` constructed, context specific
+pub(crate) const CONTEXT_0: u8 = 0xa0;
+pub(crate) const CONTEXT_1: u8 = 0xa1;
+
+/// A type-length-value element
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Tlv<'a> {
+    pub tag: u8,
+    pub content: &'a [u8],
+    /// The whole element, header included
+    pub raw: &'a [u8],
+}
+
+impl<'a> Tlv<'a> {
+    /// Reader over the elements of a constructed value
+    pub fn reader(&self) -> Der<'a> {
+        Der::new(self.content)
+    }
+}
+
+/// Sequential reader of the elements in `buf`
+#[derive(Debug, Clone)]
+pub(crate) struct Der<'a> {
+    buf: &'a [u8],
+    offset: usize,
+}
+
+impl<'a> Der<'a> {
+    pub fn new(buf: &'a [u8]) -> Self {
+        Der { buf, offset: 0 }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.offset >= self.buf.len()
+    }
+
+    fn byte(&self, offset: usize) -> Result<u8, SigningError> {
+        self.buf
+            .get(offset)
+            .copied()
+            .ok_or(SigningError::Eof(offset))
+    }
+
+    fn peek_tag(&self) -> Option<u8> {
+        self.buf.get(self.offset).copied()
+    }
+
+    pub fn read(&mut self) -> Result<Tlv<'a>, SigningError> {
+        let start = self.offset;
+        let tag = self.byte(start)?;
+        let first = self.byte(start + 1)?;
+        let (len, header) = match first {
+            0x00..=0x7f => (first as usize, 2),
+            // Indefinite lengths are BER only
+            0x80 | 0x85.. => return Err(SigningError::UnexpectedTag { offset: start, tag }),
+            _ => {
+                let count = (first & 0x7f) as usize;
+                let mut len = 0;
+                for i in 0..count {
+                    len = (len << 8) | self.byte(start + 2 + i)? as usize;
+                }
+                (len, 2 + count)
+            }
+        };
+        let end = start + header + len;
+        if end > self.buf.len() {
+            return Err(SigningError::Eof(self.buf.len()));
+        }
+        self.offset = end;
+        Ok(Tlv {
+            tag,
+            content: &self.buf[start + header..end],
+            raw: &self.buf[start..end],
+        })
+    }
+
+    /// Reads the next element, failing unless it has the given tag
+    pub fn expect(&mut self, tag: u8) -> Result<Tlv<'a>, SigningError> {
+        let offset = self.offset;
+        let tlv = self.read()?;
+        if tlv.tag == tag {
+            Ok(tlv)
+        } else {
+            Err(SigningError::UnexpectedTag {
+                offset,
+                tag: tlv.tag,
+            })
+        }
+    }
+
+    /// Reads the next element if it has the given tag
+    pub fn optional(&mut self, tag: u8) -> Result<Option<Tlv<'a>>, SigningError> {
+        if self.peek_tag() == Some(tag) {
+            self.read().map(Some)
+        } else {
+            Ok(None)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Der, SEQUENCE};
+
+    #[test]
+    fn test_read() {
+        let mut long = vec![SEQUENCE, 0x81, 0x80];
+        long.extend([0x05, 0x00].repeat(64));
+        long.extend([0x02, 0x01, 0x07]);
+        let mut der = Der::new(&long);
+        let sequence = der.expect(SEQUENCE).unwrap();
+        assert_eq!(sequence.content.len(), 128);
+        assert_eq!(sequence.raw.len(), 131);
+        assert_eq!(sequence.reader().read().unwrap().tag, 0x05);
+        assert_eq!(der.optional(SEQUENCE).unwrap().map(|t| t.tag), None);
+        assert_eq!(der.read().unwrap().content, [0x07]);
+        assert!(der.is_empty());
+
+        assert!(Der::new(&[SEQUENCE, 0x80, 0x00, 0x00]).read().is_err());
+        assert!(Der::new(&[SEQUENCE, 0x05, 0x00]).read().is_err());
+    }
+}
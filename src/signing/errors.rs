@@ -0,0 +1,23 @@
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum SigningError {
+    #[error("Failed to read archive: {0}")]
+    Io(std::io::Error),
+    #[error("Unexpected end of data at offset {0}")]
+    Eof(usize),
+    #[error("End of central directory not found")]
+    NoEndOfCentralDirectory,
+    #[error("Malformed APK Signing Block: {0}")]
+    MalformedBlock(&'static str),
+    #[error("Unexpected DER tag {tag:#04x} at offset {offset}")]
+    UnexpectedTag { offset: usize, tag: u8 },
+    #[error("Malformed certificate: {0}")]
+    Certificate(String),
+}
+
+impl From<std::io::Error> for SigningError {
+    fn from(e: std::io::Error) -> Self {
+        SigningError::Io(e)
+    }
+}
@@ -0,0 +1,495 @@
+//! v1 (JAR) signatures, the PKCS #7 signature block files (`META-INF/*.RSA`, `.DSA` or `.EC`)
+//! signing the `.SF` file of the same name, which holds the digests of `META-INF/MANIFEST.MF`
+
+use std::{
+    collections::BTreeMap,
+    io::{Read, Seek},
+};
+
+use x509_parser::{certificate::X509Certificate, prelude::FromDer};
+use zip::ZipArchive;
+
+use super::{
+    certificate::Certificate,
+    crypto::{self, Hash, Padding},
+    der::{Der, Tlv, CONTEXT_0, CONTEXT_1, INTEGER, OCTET_STRING, OID, SEQUENCE, SET},
+    errors::SigningError,
+    Signer, Verdict,
+};
+
+const MANIFEST: &str = "META-INF/MANIFEST.MF";
+
+/// Content of the `messageDigest` attribute OID (1.2.840.113549.1.9.4)
+const MESSAGE_DIGEST_OID: &[u8] = &[0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x09, 0x04];
+
+struct SignerInfo<'a> {
+    /// DER encoded `Name` of the `IssuerAndSerialNumber` identifying the certificate
+    issuer: &'a [u8],
+    /// Content of the serial number `INTEGER`
+    serial: &'a [u8],
+    /// Content of the digest algorithm OID
+    digest_algorithm: &'a [u8],
+    authenticated_attributes: Option<Tlv<'a>>,
+    signature: &'a [u8],
+}
+
+struct SignedData<'a> {
+    certificates: Vec<&'a [u8]>,
+    signer_infos: Vec<SignerInfo<'a>>,
+}
+
+fn parse_signed_data(buf: &[u8]) -> Result<SignedData<'_>, SigningError> {
+    // ContentInfo
+    let mut content_info = Der::new(buf).expect(SEQUENCE)?.reader();
+    content_info.expect(OID)?;
+    let mut signed_data = content_info
+        .expect(CONTEXT_0)?
+        .reader()
+        .expect(SEQUENCE)?
+        .reader();
+    // Version, digest algorithms and the (detached) content
+    signed_data.expect(INTEGER)?;
+    signed_data.expect(SET)?;
+    signed_data.expect(SEQUENCE)?;
+
+    let mut certificates = Vec::new();
+    if let Some(set) = signed_data.optional(CONTEXT_0)? {
+        let mut reader = set.reader();
+        while !reader.is_empty() {
+            certificates.push(reader.read()?.raw);
+        }
+    }
+    // CRLs
+    signed_data.optional(CONTEXT_1)?;
+
+    let mut signer_infos = Vec::new();
+    let mut reader = signed_data.expect(SET)?.reader();
+    while !reader.is_empty() {
+        let mut signer_info = reader.expect(SEQUENCE)?.reader();
+        signer_info.expect(INTEGER)?;
+        let mut issuer_and_serial = signer_info.expect(SEQUENCE)?.reader();
+        let issuer = issuer_and_serial.expect(SEQUENCE)?.raw;
+        let serial = issuer_and_serial.expect(INTEGER)?.content;
+        let digest_algorithm = signer_info.expect(SEQUENCE)?.reader().expect(OID)?.content;
+        let authenticated_attributes = signer_info.optional(CONTEXT_0)?;
+        // The signature algorithm follows from the key of the certificate
+        signer_info.expect(SEQUENCE)?;
+        signer_infos.push(SignerInfo {
+            issuer,
+            serial,
+            digest_algorithm,
+            authenticated_attributes,
+            signature: signer_info.expect(OCTET_STRING)?.content,
+        });
+    }
+    Ok(SignedData {
+        certificates,
+        signer_infos,
+    })
+}
+
+/// Whether the certificate is the one a `SignerInfo` refers to
+fn is_signer(der: &[u8], signer_info: &SignerInfo) -> bool {
+    X509Certificate::from_der(der).is_ok_and(|(_, cert)| {
+        cert.issuer().as_raw() == signer_info.issuer && cert.raw_serial() == signer_info.serial
+    })
+}
+
+fn is_signature_block(name: &str) -> bool {
+    let name = name.to_ascii_uppercase();
+    name.ends_with(".RSA") || name.ends_with(".DSA") || name.ends_with(".EC")
+}
+
+fn signer(path: &str, buf: &[u8]) -> Result<Signer, SigningError> {
+    let mut signed_data = parse_signed_data(buf)?;
+    // The signer's certificate first, followed by the rest of the chain
+    if let Some(signer_info) = signed_data.signer_infos.first() {
+        if let Some(i) = signed_data
+            .certificates
+            .iter()
+            .position(|c| is_signer(c, signer_info))
+        {
+            let certificate = signed_data.certificates.remove(i);
+            signed_data.certificates.insert(0, certificate);
+        }
+    }
+    Ok(Signer {
+        path: Some(path.to_string()),
+        certificates: signed_data
+            .certificates
+            .into_iter()
+            .map(Certificate::parse)
+            .collect::<Result<_, _>>()?,
+        ..Default::default()
+    })
+}
+
+/// Signers of the signature block files among the `META-INF/` entries
+pub(crate) fn signers(meta_inf: &BTreeMap<String, Vec<u8>>) -> Vec<Signer> {
+    meta_inf
+        .iter()
+        .filter(|(name, _)| is_signature_block(name))
+        .filter_map(|(name, buf)| match signer(name, buf) {
+            Ok(signer) => Some(signer),
+            Err(e) => {
+                log::warn!("Failed to parse signature block {name}: {e}");
+                None
+            }
+        })
+        .collect()
+}
+
+/// Hash of a digest algorithm OID
+fn hash(oid: &[u8]) -> Option<Hash> {
+    match oid {
+        [0x2b, 0x0e, 0x03, 0x02, 0x1a] => Some(Hash::Sha1),
+        [0x60, 0x86, 0x48, 0x01, 0x65, 0x03, 0x04, 0x02, n] => match n {
+            1 => Some(Hash::Sha256),
+            2 => Some(Hash::Sha384),
+            3 => Some(Hash::Sha512),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+fn base64(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut encoded = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let n = chunk
+            .iter()
+            .enumerate()
+            .fold(0u32, |n, (i, b)| n | (*b as u32) << (16 - 8 * i));
+        for i in 0..4 {
+            if i <= chunk.len() {
+                encoded.push(ALPHABET[(n >> (18 - 6 * i)) as usize & 0x3f] as char);
+            } else {
+                encoded.push('=');
+            }
+        }
+    }
+    encoded
+}
+
+/// A section of a manifest or signature file, the first one holds the main attributes
+struct Section<'a> {
+    /// Bytes of the section, including the blank line ending it
+    raw: &'a [u8],
+    attributes: Vec<(String, String)>,
+}
+
+impl Section<'_> {
+    fn get(&self, name: &str) -> Option<&str> {
+        self.attributes
+            .iter()
+            .find(|(n, _)| n.eq_ignore_ascii_case(name))
+            .map(|(_, v)| v.as_str())
+    }
+
+    /// The strongest supported `<algorithm><suffix>` digest (e.g. `SHA-256-Digest`), base64
+    /// encoded, or `None` if there is no digest
+    fn digest(&self, suffix: &str) -> Option<Result<(Hash, &str), Verdict>> {
+        let mut digests = self
+            .attributes
+            .iter()
+            .filter_map(|(name, value)| {
+                let algorithm = name
+                    .len()
+                    .checked_sub(suffix.len())
+                    .filter(|&i| name.is_char_boundary(i) && name[i..].eq_ignore_ascii_case(suffix))
+                    .map(|i| name[..i].to_ascii_uppercase())?;
+                Some((algorithm, value.as_str()))
+            })
+            .peekable();
+        digests.peek()?;
+        let supported = digests.filter_map(|(algorithm, value)| {
+            let hash = match algorithm.as_str() {
+                "SHA1" | "SHA-1" => Hash::Sha1,
+                "SHA-256" | "SHA256" => Hash::Sha256,
+                "SHA-384" | "SHA384" => Hash::Sha384,
+                "SHA-512" | "SHA512" => Hash::Sha512,
+                _ => return None,
+            };
+            Some((hash, value))
+        });
+        Some(
+            supported
+                .max_by_key(|(hash, _)| *hash)
+                .ok_or_else(|| Verdict::Unsupported(format!("No supported {suffix} algorithm"))),
+        )
+    }
+
+    /// Whether the `<algorithm><suffix>` digest of the section matches `content`
+    fn matches(&self, suffix: &str, content: &[u8]) -> Result<bool, Verdict> {
+        match self.digest(suffix) {
+            Some(digest) => {
+                let (hash, expected) = digest?;
+                Ok(base64(&hash.digest(&[content])) == expected)
+            }
+            None => Ok(false),
+        }
+    }
+}
+
+/// Splits a manifest or signature file into its sections
+fn sections(buf: &[u8]) -> Vec<Section<'_>> {
+    let mut sections = Vec::new();
+    let mut start = 0;
+    let mut attributes: Vec<(String, String)> = Vec::new();
+    let mut offset = 0;
+    while offset < buf.len() {
+        let end = buf[offset..]
+            .iter()
+            .position(|&b| b == b'\n')
+            .map_or(buf.len(), |i| offset + i + 1);
+        let line = String::from_utf8_lossy(&buf[offset..end]);
+        let line = line.trim_end_matches(['\r', '\n']);
+        offset = end;
+        if line.is_empty() {
+            sections.push(Section {
+                raw: &buf[start..offset],
+                attributes: std::mem::take(&mut attributes),
+            });
+            start = offset;
+        } else if let Some(continuation) = line.strip_prefix(' ') {
+            if let Some((_, value)) = attributes.last_mut() {
+                value.push_str(continuation);
+            }
+        } else if let Some((name, value)) = line.split_once(':') {
+            attributes.push((name.to_string(), value.trim_start().to_string()));
+        }
+    }
+    if !attributes.is_empty() {
+        sections.push(Section {
+            raw: &buf[start..],
+            attributes,
+        });
+    }
+    sections
+}
+
+fn failed(message: impl Into<String>) -> Verdict {
+    Verdict::Failed(message.into())
+}
+
+/// Verifies a signature block against its signature file, and the signature file against
+/// the manifest
+fn verify_block(
+    path: &str,
+    block: &[u8],
+    meta_inf: &BTreeMap<String, Vec<u8>>,
+    manifest: &[u8],
+) -> Result<(), Verdict> {
+    let sf_path = format!("{}.SF", &path[..path.rfind('.').unwrap_or(path.len())]);
+    let sf = meta_inf
+        .get(&sf_path)
+        .ok_or_else(|| failed(format!("Missing {sf_path}")))?;
+    let signed_data = parse_signed_data(block).map_err(|e| failed(e.to_string()))?;
+    let signer_info = signed_data
+        .signer_infos
+        .first()
+        .ok_or_else(|| failed(format!("No signer in {path}")))?;
+    let certificate = signed_data
+        .certificates
+        .iter()
+        .find(|c| is_signer(c, signer_info))
+        .ok_or_else(|| failed(format!("Signer certificate missing from {path}")))?;
+    let (_, certificate) =
+        X509Certificate::from_der(certificate).map_err(|e| failed(e.to_string()))?;
+    let hash = hash(signer_info.digest_algorithm)
+        .ok_or_else(|| Verdict::Unsupported(format!("Unsupported digest algorithm in {path}")))?;
+
+    // With authenticated attributes their DER encoding (as a SET) is signed instead
+    let signed = match signer_info.authenticated_attributes {
+        Some(attributes) => {
+            let mut reader = attributes.reader();
+            let mut message_digest = None;
+            while !reader.is_empty() {
+                let mut attribute = reader
+                    .expect(SEQUENCE)
+                    .map_err(|e| failed(e.to_string()))?
+                    .reader();
+                if attribute.expect(OID).map(|o| o.content).ok() == Some(MESSAGE_DIGEST_OID) {
+                    message_digest = attribute
+                        .expect(SET)
+                        .and_then(|set| set.reader().expect(OCTET_STRING))
+                        .map(|digest| digest.content)
+                        .ok();
+                }
+            }
+            if message_digest != Some(hash.digest(&[sf]).as_slice()) {
+                return Err(failed(format!("{sf_path} does not match {path}")));
+            }
+            let mut signed = attributes.raw.to_vec();
+            signed[0] = SET;
+            signed
+        }
+        None => sf.clone(),
+    };
+    crypto::verify(
+        certificate.public_key().raw,
+        hash,
+        Padding::Pkcs1,
+        &signed,
+        signer_info.signature,
+    )?;
+
+    let sf_sections = sections(sf);
+    let main = sf_sections
+        .first()
+        .ok_or_else(|| failed(format!("Empty {sf_path}")))?;
+    if main.matches("-Digest-Manifest", manifest)? {
+        return Ok(());
+    }
+    // Otherwise every entry of the manifest has to match its section of the signature file
+    for section in sections(manifest).iter().skip(1) {
+        let name = section.get("Name").unwrap_or_default();
+        let signed = sf_sections
+            .iter()
+            .skip(1)
+            .find(|s| s.get("Name") == Some(name))
+            .ok_or_else(|| failed(format!("{name} is not in {sf_path}")))?;
+        if !signed.matches("-Digest", section.raw)? {
+            return Err(failed(format!(
+                "{sf_path} does not match the manifest of {name}"
+            )));
+        }
+    }
+    Ok(())
+}
+
+/// Checks the digest of every entry listed in the manifest, and that every entry is listed
+fn verify_entries<R: Read + Seek>(
+    archive: &mut ZipArchive<R>,
+    manifest: &[u8],
+) -> Result<(), Verdict> {
+    let sections = sections(manifest);
+    for section in sections.iter().skip(1) {
+        let Some(name) = section.get("Name") else {
+            continue;
+        };
+        let mut content = Vec::new();
+        archive
+            .by_name(name)
+            .map_err(|_| failed(format!("{name} is signed but missing")))?
+            .read_to_end(&mut content)
+            .map_err(|e| failed(format!("Failed to read {name}: {e}")))?;
+        if !section.matches("-Digest", &content)? {
+            return Err(failed(format!(
+                "Digest of {name} does not match, the APK was modified"
+            )));
+        }
+    }
+    let unsigned = archive.file_names().find(|name| {
+        !name.starts_with("META-INF/")
+            && !name.ends_with('/')
+            && !sections.iter().any(|s| s.get("Name") == Some(name))
+    });
+    match unsigned {
+        Some(name) => Err(failed(format!("{name} is not covered by the signature"))),
+        None => Ok(()),
+    }
+}
+
+/// Verifies the v1 signature, returning the schemes the signature files state the APK is
+/// signed with as well (`X-Android-APK-Signed`), to detect stripped signatures.
+pub(crate) fn verify<R: Read + Seek>(
+    archive: &mut ZipArchive<R>,
+    meta_inf: &BTreeMap<String, Vec<u8>>,
+) -> Result<Vec<u32>, Verdict> {
+    let blocks: Vec<_> = meta_inf
+        .iter()
+        .filter(|(name, _)| is_signature_block(name))
+        .collect();
+    if blocks.is_empty() {
+        return Err(Verdict::Unsigned);
+    }
+    let manifest = meta_inf
+        .get(MANIFEST)
+        .ok_or_else(|| failed(format!("Missing {MANIFEST}")))?;
+    let mut schemes = Vec::new();
+    for (path, block) in blocks {
+        verify_block(path, block, meta_inf, manifest)?;
+        let sf = &meta_inf[&format!("{}.SF", &path[..path.rfind('.').unwrap_or(path.len())])];
+        if let Some(signed) = sections(sf)
+            .first()
+            .and_then(|main| main.get("X-Android-APK-Signed"))
+        {
+            schemes.extend(
+                signed
+                    .split(',')
+                    .filter_map(|s| s.trim().parse::<u32>().ok()),
+            );
+        }
+    }
+    verify_entries(archive, manifest)?;
+    Ok(schemes)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{Cursor, Write};
+
+    use zip::{write::SimpleFileOptions, ZipArchive, ZipWriter};
+
+    use super::{base64, sections, verify_entries};
+    use crate::signing::{crypto::Hash, Verdict};
+
+    #[test]
+    fn test_base64() {
+        assert_eq!(base64(b""), "");
+        assert_eq!(base64(b"f"), "Zg==");
+        assert_eq!(base64(b"fo"), "Zm8=");
+        assert_eq!(base64(b"foobar"), "Zm9vYmFy");
+    }
+
+    #[test]
+    fn test_sections() {
+        let manifest = b"Manifest-Version: 1.0\r\n\r\nName: res/a-very-long-na\r\n me.png\r\nSHA-256-Digest: x\r\n\r\nName: b\n";
+        let sections = sections(manifest);
+        assert_eq!(sections.len(), 3);
+        assert_eq!(sections[0].get("manifest-version"), Some("1.0"));
+        assert_eq!(sections[1].get("Name"), Some("res/a-very-long-name.png"));
+        assert_eq!(
+            sections[1].raw,
+            b"Name: res/a-very-long-na\r\n me.png\r\nSHA-256-Digest: x\r\n\r\n"
+        );
+        assert_eq!(sections[2].get("Name"), Some("b"));
+    }
+
+    #[test]
+    fn test_verify_entries() {
+        let mut writer = ZipWriter::new(Cursor::new(Vec::new()));
+        for (name, content) in [("a.txt", "a"), ("b.txt", "b")] {
+            writer
+                .start_file(name, SimpleFileOptions::default())
+                .unwrap();
+            writer.write_all(content.as_bytes()).unwrap();
+        }
+        let mut archive = ZipArchive::new(writer.finish().unwrap()).unwrap();
+
+        let section = |name: &str, content: &[u8]| {
+            let digest = base64(&Hash::Sha256.digest(&[content]));
+            format!("Name: {name}\r\nSHA-256-Digest: {digest}\r\n\r\n")
+        };
+        let main = "Manifest-Version: 1.0\r\n\r\n";
+        let manifest = format!("{main}{}{}", section("a.txt", b"a"), section("b.txt", b"b"));
+        assert_eq!(verify_entries(&mut archive, manifest.as_bytes()), Ok(()));
+
+        let manifest = format!("{main}{}{}", section("a.txt", b"a"), section("b.txt", b"c"));
+        assert_eq!(
+            verify_entries(&mut archive, manifest.as_bytes()),
+            Err(Verdict::Failed(
+                "Digest of b.txt does not match, the APK was modified".into()
+            ))
+        );
+        let manifest = format!("{main}{}", section("a.txt", b"a"));
+        assert_eq!(
+            verify_entries(&mut archive, manifest.as_bytes()),
+            Err(Verdict::Failed(
+                "b.txt is not covered by the signature".into()
+            ))
+        );
+    }
+}
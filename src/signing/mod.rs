@@ -0,0 +1,327 @@
+//! APK signatures: v1 (JAR signing) and the v2, v3 and v3.1 schemes of the APK Signing Block, see
+//! <https://source.android.com/docs/security/features/apksigning>
+
+pub(crate) mod block;
+pub(crate) mod certificate;
+mod crypto;
+mod der;
+pub(crate) mod errors;
+mod jar;
+
+use std::{
+    collections::BTreeMap,
+    io::{Read, Seek},
+};
+
+use serde::Serialize;
+use zip::ZipArchive;
+
+use self::{certificate::Certificate, crypto::Hash};
+
+/// Capabilities a lineage node grants to the certificates after it
+const CAPABILITIES: [(u32, &str); 5] = [
+    (1, "installedData"),
+    (2, "sharedUid"),
+    (4, "permission"),
+    (8, "rollback"),
+    (16, "auth"),
+];
+
+/// Signers of each signature scheme the APK is signed with
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize)]
+pub struct Signatures {
+    /// One signer per signature block file in `META-INF/`
+    pub v1: Vec<Signer>,
+    pub v2: Vec<Signer>,
+    pub v3: Vec<Signer>,
+    /// Used instead of v3 from `min_sdk` on, rotated keys only apply to Android 13+ this way
+    pub v31: Vec<Signer>,
+}
+
+/// Outcome of verifying a signature scheme
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize)]
+pub enum Verdict {
+    /// The APK is not signed with the scheme
+    #[default]
+    Unsigned,
+    Verified,
+    /// The signature is invalid or does not match the content, with the reason
+    Failed(String),
+    /// The signature relies on an algorithm this crate cannot check (e.g. DSA)
+    Unsupported(String),
+}
+
+/// Verdict of each signature scheme, checked against the content of the APK
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize)]
+pub struct Verification {
+    pub v1: Verdict,
+    pub v2: Verdict,
+    pub v3: Verdict,
+    pub v31: Verdict,
+}
+
+impl Verification {
+    /// Whether a scheme verified and none failed
+    pub fn is_verified(&self) -> bool {
+        let verdicts = [&self.v1, &self.v2, &self.v3, &self.v31];
+        verdicts.contains(&&Verdict::Verified)
+            && !verdicts.iter().any(|v| matches!(v, Verdict::Failed(_)))
+    }
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize)]
+pub struct Signer {
+    /// Signature block file of a v1 signer (e.g. `META-INF/CERT.RSA`)
+    pub path: Option<String>,
+    /// Certificate chain, the signing certificate first
+    pub certificates: Vec<Certificate>,
+    /// Lowest API level a v3 or v3.1 signer applies to
+    pub min_sdk: Option<u32>,
+    /// Highest API level a v3 or v3.1 signer applies to
+    pub max_sdk: Option<u32>,
+    /// Past signing certificates of a v3 or v3.1 signer (key rotation), oldest first and
+    /// ending with the current one
+    pub lineage: Vec<LineageNode>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct LineageNode {
+    pub certificate: Certificate,
+    /// Capabilities granted to the certificate, see `LineageNode::capabilities`
+    pub flags: u32,
+}
+
+impl LineageNode {
+    /// Names of the capabilities granted to the certificate (e.g. `sharedUid`)
+    pub fn capabilities(&self) -> Vec<&'static str> {
+        CAPABILITIES
+            .iter()
+            .filter(|(flag, _)| self.flags & flag != 0)
+            .map(|(_, name)| *name)
+            .collect()
+    }
+}
+
+impl Signer {
+    /// The certificate the signer signs with
+    pub fn certificate(&self) -> Option<&Certificate> {
+        self.certificates.first()
+    }
+}
+
+impl Signatures {
+    pub fn is_empty(&self) -> bool {
+        self.v1.is_empty() && self.v2.is_empty() && self.v3.is_empty() && self.v31.is_empty()
+    }
+}
+
+pub(crate) fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{b:02x}")).collect()
+}
+
+/// Reads and verifies the signatures of an APK, logging any error reading them.
+///
+/// ### Arguments
+/// * `archive`: The APK.
+/// * `meta_inf`: Content of the `META-INF/` entries by name.
+pub(crate) fn read<R: Read + Seek>(
+    mut archive: ZipArchive<R>,
+    meta_inf: &BTreeMap<String, Vec<u8>>,
+) -> (Signatures, Verification) {
+    let mut signatures = Signatures {
+        v1: jar::signers(meta_inf),
+        ..Default::default()
+    };
+    let mut verification = Verification::default();
+    let v1 = jar::verify(&mut archive, meta_inf);
+
+    let mut reader = archive.into_inner();
+    let block = block::find_sections(&mut reader).and_then(|sections| {
+        let block = block::read_block(&mut reader, &sections)?;
+        Ok(block.map(|block| (sections, block)))
+    });
+    match block {
+        Ok(Some((sections, block))) => {
+            // Content digests are shared by the schemes
+            let mut digests: BTreeMap<Hash, Vec<u8>> = BTreeMap::new();
+            let mut digest = |hash| match digests.get(&hash) {
+                Some(digest) => Ok(digest.clone()),
+                None => {
+                    let digest = block::content_digest(&mut reader, &sections, block.offset, hash)?;
+                    digests.insert(hash, digest.clone());
+                    Ok(digest)
+                }
+            };
+            for (id, value) in &block.pairs {
+                let (signers, verdict) = match *id {
+                    block::V2_ID => (&mut signatures.v2, &mut verification.v2),
+                    block::V3_ID => (&mut signatures.v3, &mut verification.v3),
+                    block::V31_ID => (&mut signatures.v31, &mut verification.v31),
+                    _ => continue,
+                };
+                match block::signers(*id, value) {
+                    Ok(decoded) => *signers = decoded,
+                    Err(e) => log::warn!("Failed to decode signature scheme block {id:#010x}: {e}"),
+                }
+                *verdict = block::verify(*id, value, &mut digest);
+            }
+        }
+        Ok(None) => {}
+        Err(e) => {
+            log::warn!("Failed to read the APK Signing Block: {e}");
+            verification.v2 = Verdict::Failed(e.to_string());
+        }
+    }
+
+    verification.v1 = match v1 {
+        Ok(schemes) => {
+            // Signature files of APKs also signed with v2 or v3 state it, so that removing
+            // the signing block does not downgrade the verification to v1
+            let stripped = schemes.iter().find(|scheme| match scheme {
+                2 => verification.v2 == Verdict::Unsigned,
+                3 => verification.v3 == Verdict::Unsigned,
+                _ => false,
+            });
+            match stripped {
+                Some(scheme) => Verdict::Failed(format!("The v{scheme} signature was stripped")),
+                None => Verdict::Verified,
+            }
+        }
+        Err(verdict) => verdict,
+    };
+    (signatures, verification)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        collections::BTreeMap,
+        fs::File,
+        io::{Cursor, Read},
+    };
+
+    use zip::ZipArchive;
+
+    use super::{read, Signatures, Verdict, Verification};
+
+    const OLD_SHA256: &str = "ae0deef5ad10b00f470e63d91072516a66860d949aa27ba716e9adb52d05ff96";
+    const NEW_SHA256: &str = "42ad4c7a9d08a8d64c0003268159ddc1d0349c5f522381fad86ef0a72774e5dc";
+
+    fn read_apk(buf: Vec<u8>) -> (Signatures, Verification) {
+        let mut archive = ZipArchive::new(Cursor::new(buf)).unwrap();
+        let mut meta_inf = BTreeMap::new();
+        for i in 0..archive.len() {
+            let mut file = archive.by_index(i).unwrap();
+            if file.name().starts_with("META-INF/") {
+                let mut buf = Vec::new();
+                file.read_to_end(&mut buf).unwrap();
+                meta_inf.insert(file.name().to_string(), buf);
+            }
+        }
+        read(archive, &meta_inf)
+    }
+
+    fn signed_apk() -> Vec<u8> {
+        let mut buf = Vec::new();
+        File::open("tests/apk/signed.apk")
+            .unwrap()
+            .read_to_end(&mut buf)
+            .unwrap();
+        buf
+    }
+
+    #[test]
+    fn test_read() {
+        let (signatures, verification) = read_apk(signed_apk());
+        assert_eq!(signatures.v1.len(), 1);
+        assert_eq!(signatures.v1[0].path.as_deref(), Some("META-INF/CERT.RSA"));
+        let certificate = signatures.v1[0].certificate().unwrap();
+        assert_eq!(certificate.subject, "C=US, O=Example, CN=Old Key");
+        assert_eq!(certificate.issuer, certificate.subject);
+        assert_eq!(certificate.serial_number, "1001");
+        // 2020-01-01 to 2050-01-01
+        assert_eq!(certificate.not_before, 1577836800);
+        assert_eq!(certificate.not_after, 2524608000);
+        assert_eq!(certificate.key_algorithm, "RSA");
+        assert_eq!(certificate.key_size, 2048);
+        assert_eq!(certificate.sha1, "6e4dba0da02ac5aa8685dae3bafad7a29ea0b239");
+        assert_eq!(certificate.sha256, OLD_SHA256);
+
+        assert_eq!(signatures.v2.len(), 1);
+        assert_eq!(signatures.v2[0].certificates, signatures.v1[0].certificates);
+        assert_eq!(signatures.v2[0].min_sdk, None);
+
+        // The old key up to Android 12L, then the rotated one
+        assert_eq!(signatures.v3.len(), 1);
+        assert_eq!(signatures.v3[0].certificate().unwrap().sha256, OLD_SHA256);
+        assert_eq!(signatures.v3[0].max_sdk, Some(32));
+        assert!(signatures.v3[0].lineage.is_empty());
+
+        let signer = &signatures.v31[0];
+        let certificate = signer.certificate().unwrap();
+        assert_eq!(certificate.subject, "C=US, O=Example, CN=New Key");
+        assert_eq!(certificate.key_algorithm, "EC");
+        assert_eq!(certificate.key_size, 256);
+        assert_eq!(certificate.sha256, NEW_SHA256);
+        assert_eq!(signer.min_sdk, Some(33));
+        let lineage: Vec<_> = signer
+            .lineage
+            .iter()
+            .map(|n| n.certificate.sha256.as_str())
+            .collect();
+        assert_eq!(lineage, [OLD_SHA256, NEW_SHA256]);
+        assert_eq!(
+            signer.lineage[0].capabilities(),
+            ["installedData", "sharedUid", "permission", "auth"]
+        );
+
+        assert_eq!(
+            verification,
+            Verification {
+                v1: Verdict::Verified,
+                v2: Verdict::Verified,
+                v3: Verdict::Verified,
+                v31: Verdict::Verified,
+            }
+        );
+        assert!(verification.is_verified());
+    }
+
+    /// Offset of the first occurrence of `needle`
+    fn find(buf: &[u8], needle: &[u8]) -> usize {
+        buf.windows(needle.len()).position(|w| w == needle).unwrap()
+    }
+
+    #[test]
+    fn test_verify_tampered() {
+        // A stored entry modified in place, which its CRC gives away already
+        let mut apk = signed_apk();
+        let i = find(&apk, b"hello\n");
+        apk[i] = b'j';
+        let (_, verification) = read_apk(apk);
+        assert_eq!(
+            verification.v1,
+            Verdict::Failed("Failed to read res/raw/hello.txt: Invalid checksum".into())
+        );
+        for verdict in [verification.v2, verification.v3, verification.v31] {
+            assert_eq!(
+                verdict,
+                Verdict::Failed("Content digest does not match, the APK was modified".into())
+            );
+        }
+
+        // The signing block replaced by padding of the same size
+        let mut apk = signed_apk();
+        let end = find(&apk, b"APK Sig Block 42") + 16;
+        let size = u64::from_le_bytes(apk[end - 24..end - 16].try_into().unwrap()) as usize;
+        apk[end - size - 8..end].fill(0);
+        let (signatures, verification) = read_apk(apk);
+        assert!(signatures.v2.is_empty());
+        assert_eq!(verification.v2, Verdict::Unsigned);
+        assert_eq!(
+            verification.v1,
+            Verdict::Failed("The v2 signature was stripped".into())
+        );
+        assert!(!verification.is_verified());
+    }
+}
@@ -0,0 +1,57 @@
+//! Types of the attribute values of binary XML, `axmldecoder` only renders them as strings
+
+use std::collections::HashMap;
+
+use super::ValueType;
+use crate::resources::{
+    chunk::{read_u16, read_u32, read_u8, Chunk, RES_STRING_POOL_TYPE},
+    errors::ResourceError,
+    string_pool::StringPool,
+};
+
+const RES_XML_START_NAMESPACE_TYPE: u16 = 0x0100;
+const RES_XML_START_ELEMENT_TYPE: u16 = 0x0102;
+
+/// Attribute types of each element in document order, attributes are named like `axmldecoder`
+/// does (e.g. `android:name`)
+pub(super) fn attribute_types(
+    buf: &[u8],
+) -> Result<Vec<HashMap<String, ValueType>>, ResourceError> {
+    let mut strings = StringPool::default();
+    // Prefix of each namespace, by the index of its URI
+    let mut prefixes = HashMap::new();
+    let mut elements = Vec::new();
+    for chunk in Chunk::read(buf, 0)?.children(buf) {
+        let chunk = chunk?;
+        let data = chunk.data(buf);
+        // Extension following the `ResXMLTree_node` header
+        let ext = chunk.header_size;
+        match chunk.chunk_type {
+            RES_STRING_POOL_TYPE => strings = StringPool::parse(buf, chunk)?,
+            RES_XML_START_NAMESPACE_TYPE => {
+                let prefix = strings.get(read_u32(data, ext)?)?.to_string();
+                prefixes.insert(read_u32(data, ext + 4)?, prefix);
+            }
+            RES_XML_START_ELEMENT_TYPE => {
+                let start = ext + read_u16(data, ext + 8)? as usize;
+                let size = read_u16(data, ext + 10)? as usize;
+                let count = read_u16(data, ext + 12)? as usize;
+                let mut types = HashMap::with_capacity(count);
+                for i in 0..count {
+                    let offset = start + i * size;
+                    let name = strings.get(read_u32(data, offset + 4)?)?;
+                    let name = match prefixes.get(&read_u32(data, offset)?) {
+                        Some(prefix) => format!("{prefix}:{name}"),
+                        None => name.to_string(),
+                    };
+                    // `Res_value` after the namespace, name and raw value
+                    let data_type = read_u8(data, offset + 15)?;
+                    types.insert(name, ValueType::from_data_type(data_type));
+                }
+                elements.push(types);
+            }
+            _ => {}
+        }
+    }
+    Ok(elements)
+}
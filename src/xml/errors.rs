@@ -0,0 +1,18 @@
+use axmldecoder::ParseError;
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum XmlError {
+    #[error("Malformed binary XML: {0}")]
+    Binary(ParseError),
+    #[error("Malformed XML at offset {offset}: {message}")]
+    Text { offset: usize, message: String },
+    #[error("Malformed protobuf XML at offset {offset}: {message}")]
+    Proto { offset: usize, message: String },
+}
+
+impl From<ParseError> for XmlError {
+    fn from(e: ParseError) -> Self {
+        XmlError::Binary(e)
+    }
+}
@@ -0,0 +1,262 @@
+mod binary;
+pub(crate) mod errors;
+mod proto;
+mod text;
+
+use std::collections::{BTreeMap, HashMap};
+
+use axmldecoder::{Element, Node, XmlDocument};
+use serde::Serialize;
+
+use self::errors::XmlError;
+use crate::{
+    manifest::value::{parse_int, parse_reference, ResourceRef},
+    resources::{value::ResValue, ResourceTable},
+};
+
+const ANDROID_NAMESPACE: &str = "http://schemas.android.com/apk/res/android";
+/// Deepest nesting of elements the text and protobuf parsers accept, they recurse per element
+const MAX_DEPTH: usize = 256;
+
+/// An element as written in the document, binary and text XML both decode to it
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub(crate) struct RawElement {
+    pub tag: String,
+    /// Attribute values by name, typed values of binary XML are rendered as strings
+    pub attributes: HashMap<String, String>,
+    /// Types the attribute values are compiled to, missing when unknown
+    pub types: HashMap<String, ValueType>,
+    pub children: Vec<RawElement>,
+    pub text: String,
+}
+
+/// Type of a compiled attribute value, `Res_value` in binary XML and `Item` in protobuf XML
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum ValueType {
+    String,
+    Integer,
+    Boolean,
+    Reference,
+    /// Floats, dimensions, colors, ...
+    Other,
+}
+
+impl ValueType {
+    /// Type of a `Res_value` data type
+    fn from_data_type(data_type: u8) -> Self {
+        match data_type {
+            0x01 | 0x07 => ValueType::Reference,
+            0x03 => ValueType::String,
+            0x10 | 0x11 => ValueType::Integer,
+            0x12 => ValueType::Boolean,
+            _ => ValueType::Other,
+        }
+    }
+
+    /// Type aapt2 compiles a value of the source XML to, for attributes accepting references,
+    /// booleans, integers and strings (e.g. `android:value` of a `<meta-data>`)
+    fn infer(value: &str) -> Self {
+        if parse_reference(value).is_some() {
+            ValueType::Reference
+        } else if matches!(value.trim(), "true" | "false") {
+            ValueType::Boolean
+        } else if parse_int(value).is_some() {
+            ValueType::Integer
+        } else {
+            ValueType::String
+        }
+    }
+}
+
+impl RawElement {
+    /// Converts a decoded binary XML element, `types` holding the attribute types of the elements
+    /// in document order
+    fn from_binary(
+        element: Element,
+        types: &mut impl Iterator<Item = HashMap<String, ValueType>>,
+    ) -> Self {
+        let mut raw = RawElement {
+            tag: element.get_tag().to_string(),
+            types: types.next().unwrap_or_default(),
+            ..Default::default()
+        };
+        raw.attributes.extend(element.attributes);
+        for child in element.children {
+            match child {
+                Node::Element(element) => raw.children.push(Self::from_binary(element, types)),
+                Node::Cdata(cdata) => raw.text.push_str(cdata.get_data()),
+            }
+        }
+        raw
+    }
+}
+
+/// Decodes binary XML, or plain-text XML if the buffer does not start with a binary XML header
+pub(crate) fn parse_raw(buf: &[u8]) -> Result<Option<RawElement>, XmlError> {
+    // RES_XML_TYPE chunk
+    if buf.starts_with(&[0x03, 0x00]) {
+        let XmlDocument { root } = axmldecoder::parse(buf)?;
+        let types = binary::attribute_types(buf).unwrap_or_else(|e| {
+            log::warn!("Attribute types of binary XML: {e}");
+            Vec::new()
+        });
+        match root {
+            Some(Node::Element(root)) => {
+                Ok(Some(RawElement::from_binary(root, &mut types.into_iter())))
+            }
+            Some(other) => {
+                log::warn!("Unexpected root node: {other:?}");
+                Ok(None)
+            }
+            None => Ok(None),
+        }
+    } else {
+        let text = std::str::from_utf8(buf).map_err(|e| XmlError::Text {
+            offset: e.valid_up_to(),
+            message: "Invalid UTF-8".to_string(),
+        })?;
+        text::parse(text).map(Some)
+    }
+}
+
+/// Decodes the protobuf XML (aapt2 `XmlNode`) of an Android App Bundle
+pub(crate) fn parse_proto(buf: &[u8]) -> Result<Option<RawElement>, XmlError> {
+    proto::parse(buf)
+}
+
+/// Element of a decoded binary XML file (e.g. a layout or a `res/xml` configuration)
+#[derive(Debug, Default, Clone, PartialEq, Serialize)]
+pub struct XmlElement {
+    pub tag: String,
+    /// Attributes by name, with their namespace prefix (e.g. `android:name`)
+    pub attributes: BTreeMap<String, XmlValue>,
+    pub children: Vec<XmlElement>,
+    /// Character data of the element, concatenated
+    pub text: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub enum XmlValue {
+    Literal(String),
+    /// A resource reference with its value in the default configuration, when it resolves
+    Resource {
+        reference: ResourceRef,
+        value: Option<ResValue>,
+    },
+}
+
+impl XmlValue {
+    /// The literal, or the resolved value if it is a string (e.g. the path of an XML resource)
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            XmlValue::Literal(s)
+            | XmlValue::Resource {
+                value: Some(ResValue::String(s)),
+                ..
+            } => Some(s),
+            XmlValue::Resource { .. } => None,
+        }
+    }
+}
+
+impl XmlElement {
+    fn new(element: RawElement, resources: Option<&ResourceTable>) -> Self {
+        let attributes = element
+            .attributes
+            .into_iter()
+            .map(|(name, value)| {
+                let value = match parse_reference(&value) {
+                    Some(reference) => XmlValue::Resource {
+                        value: resources
+                            .and_then(|r| r.resolve_default(&reference))
+                            .cloned(),
+                        reference,
+                    },
+                    None => XmlValue::Literal(value),
+                };
+                (name, value)
+            })
+            .collect();
+
+        XmlElement {
+            tag: element.tag,
+            attributes,
+            children: element
+                .children
+                .into_iter()
+                .map(|c| XmlElement::new(c, resources))
+                .collect(),
+            text: element.text,
+        }
+    }
+
+    /// Literal or resolved string value of an attribute, see `XmlValue::as_str`
+    pub fn attribute(&self, name: &str) -> Option<&str> {
+        self.attributes.get(name)?.as_str()
+    }
+
+    /// Children with the given tag
+    pub fn children_by_tag<'a>(&'a self, tag: &'a str) -> impl Iterator<Item = &'a XmlElement> {
+        self.children.iter().filter(move |c| c.tag == tag)
+    }
+}
+
+/// Decodes a binary (or plain-text) XML file into its root element.
+///
+/// ### Arguments
+/// * `buf`: Content of the file.
+/// * `resources`: Table the resource references are resolved with, if any.
+pub fn parse_xml(
+    buf: &[u8],
+    resources: Option<&ResourceTable>,
+) -> Result<Option<XmlElement>, XmlError> {
+    Ok(parse_raw(buf)?.map(|root| XmlElement::new(root, resources)))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs::File, io::Read};
+
+    use super::{parse_xml, XmlValue};
+    use crate::{
+        manifest::value::ResourceRef,
+        resources::{value::ResValue, ResourceTable},
+    };
+
+    fn read(path: &str) -> Vec<u8> {
+        let mut buf = Vec::new();
+        File::open(path).unwrap().read_to_end(&mut buf).unwrap();
+        buf
+    }
+
+    #[test]
+    fn test_parse_xml() {
+        let resources = ResourceTable::parse(&read("tests/resources/a.arsc")).unwrap();
+        let root = parse_xml(&read("tests/manifest/d.xml"), Some(&resources))
+            .unwrap()
+            .unwrap();
+        assert_eq!(root.tag, "manifest");
+        assert_eq!(root.attribute("package"), Some("com.example.rich"));
+
+        let application = root.children_by_tag("application").next().unwrap();
+        assert_eq!(
+            application.attributes["android:label"],
+            XmlValue::Resource {
+                reference: ResourceRef::Id(0x7f100000),
+                value: Some(ResValue::String("Rich App".into())),
+            }
+        );
+        assert_eq!(
+            application.attribute("android:networkSecurityConfig"),
+            Some("res/xml/network_security_config.xml")
+        );
+        assert_eq!(application.children_by_tag("activity").count(), 2);
+
+        // Unresolved without a resource table
+        let root = parse_xml(&read("tests/manifest/d.xml"), None)
+            .unwrap()
+            .unwrap();
+        let application = root.children_by_tag("application").next().unwrap();
+        assert_eq!(application.attribute("android:label"), None);
+    }
+}
@@ -0,0 +1,321 @@
+//! Protobuf XML of Android App Bundles, the `XmlNode` message of aapt2's `Resources.proto`
+
+use super::{errors::XmlError, RawElement, ValueType, ANDROID_NAMESPACE, MAX_DEPTH};
+
+/// Value of a field, by wire type
+enum Field<'a> {
+    Varint(u64),
+    Fixed32(u32),
+    Fixed64,
+    Bytes(Fields<'a>),
+}
+
+/// Reader of the fields of a message
+struct Fields<'a> {
+    buf: &'a [u8],
+    position: usize,
+    /// Offset of the message in the file, for errors
+    base: usize,
+}
+
+impl<'a> Fields<'a> {
+    fn new(buf: &'a [u8], base: usize) -> Self {
+        Fields {
+            buf,
+            position: 0,
+            base,
+        }
+    }
+
+    fn error(&self, message: &str) -> XmlError {
+        XmlError::Proto {
+            offset: self.base + self.position,
+            message: message.to_string(),
+        }
+    }
+
+    fn take(&mut self, len: usize) -> Result<&'a [u8], XmlError> {
+        let bytes = self
+            .buf
+            .get(self.position..self.position.saturating_add(len))
+            .ok_or_else(|| self.error("Unexpected end of message"))?;
+        self.position += len;
+        Ok(bytes)
+    }
+
+    fn varint(&mut self) -> Result<u64, XmlError> {
+        let mut value = 0;
+        for shift in (0..64).step_by(7) {
+            let byte = self.take(1)?[0];
+            value |= u64::from(byte & 0x7f) << shift;
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+        Err(self.error("Varint longer than 10 bytes"))
+    }
+
+    fn field(&mut self) -> Result<(u64, Field<'a>), XmlError> {
+        let key = self.varint()?;
+        let value = match key & 7 {
+            0 => Field::Varint(self.varint()?),
+            1 => {
+                self.take(8)?;
+                Field::Fixed64
+            }
+            2 => {
+                let len = self.varint()? as usize;
+                let base = self.base + self.position;
+                Field::Bytes(Fields::new(self.take(len)?, base))
+            }
+            5 => Field::Fixed32(u32::from_le_bytes(self.take(4)?.try_into().unwrap())),
+            _ => return Err(self.error("Unsupported wire type")),
+        };
+        Ok((key >> 3, value))
+    }
+
+    /// The message as a string field
+    fn string(self) -> Result<String, XmlError> {
+        String::from_utf8(self.buf.to_vec()).map_err(|_| self.error("Invalid UTF-8"))
+    }
+}
+
+impl<'a> Iterator for Fields<'a> {
+    type Item = Result<(u64, Field<'a>), XmlError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.position >= self.buf.len() {
+            return None;
+        }
+        let field = self.field();
+        if field.is_err() {
+            // Stop after an error, the position is not reliable anymore
+            self.position = self.buf.len();
+        }
+        Some(field)
+    }
+}
+
+/// Decodes an `XmlNode`, `None` if it is not an element
+pub(crate) fn parse(buf: &[u8]) -> Result<Option<RawElement>, XmlError> {
+    let mut root = None;
+    for field in Fields::new(buf, 0) {
+        if let (1, Field::Bytes(fields)) = field? {
+            root = Some(element(fields, &mut Vec::new(), 0)?);
+        }
+    }
+    Ok(root)
+}
+
+/// Decodes an `XmlElement` with `depth` ancestors, `namespaces` being the URI and prefix of
+/// each declaration in scope, innermost last. The declarations of the element are popped
+/// once it is decoded.
+fn element(
+    fields: Fields,
+    namespaces: &mut Vec<(String, String)>,
+    depth: usize,
+) -> Result<RawElement, XmlError> {
+    if depth == MAX_DEPTH {
+        return Err(fields.error(&format!("Elements nested deeper than {MAX_DEPTH}")));
+    }
+    let scope = namespaces.len();
+    let mut raw = RawElement::default();
+    let mut attributes = Vec::new();
+    let mut children = Vec::new();
+    for field in fields {
+        match field? {
+            (1, Field::Bytes(declaration)) => {
+                let (mut prefix, mut uri) = (String::new(), String::new());
+                for field in declaration {
+                    match field? {
+                        (1, Field::Bytes(value)) => prefix = value.string()?,
+                        (2, Field::Bytes(value)) => uri = value.string()?,
+                        _ => {}
+                    }
+                }
+                namespaces.push((uri, prefix));
+            }
+            (3, Field::Bytes(name)) => raw.tag = name.string()?,
+            (4, Field::Bytes(attribute)) => attributes.push(attribute),
+            (5, Field::Bytes(child)) => children.push(child),
+            _ => {}
+        }
+    }
+
+    // Declarations may come after the attributes using them
+    for fields in attributes {
+        let (name, value, value_type) = attribute(fields, namespaces)?;
+        if let Some(value_type) = value_type {
+            raw.types.insert(name.clone(), value_type);
+        }
+        raw.attributes.insert(name, value);
+    }
+    for child in children {
+        for field in child {
+            match field? {
+                (1, Field::Bytes(fields)) => {
+                    raw.children.push(element(fields, namespaces, depth + 1)?)
+                }
+                (2, Field::Bytes(text)) => raw.text.push_str(&text.string()?),
+                _ => {}
+            }
+        }
+    }
+    namespaces.truncate(scope);
+    Ok(raw)
+}
+
+/// Value of an attribute compiled by aapt2
+enum Compiled {
+    /// Resource id of a reference
+    Reference(u32),
+    /// Rendering of a primitive or string
+    Value(String, ValueType),
+}
+
+/// Decodes an `XmlAttribute` into its name, value and compiled type. The value is rendered like
+/// the ones of binary XML: references by id, then the value as written in the source, then the
+/// compiled value.
+fn attribute(
+    fields: Fields,
+    namespaces: &[(String, String)],
+) -> Result<(String, String, Option<ValueType>), XmlError> {
+    let (mut uri, mut name, mut value, mut compiled) =
+        (String::new(), String::new(), String::new(), None);
+    for field in fields {
+        match field? {
+            (1, Field::Bytes(field)) => uri = field.string()?,
+            (2, Field::Bytes(field)) => name = field.string()?,
+            (3, Field::Bytes(field)) => value = field.string()?,
+            (6, Field::Bytes(item)) => compiled = self::item(item)?,
+            _ => {}
+        }
+    }
+
+    let name = match uri.as_str() {
+        "" => name,
+        ANDROID_NAMESPACE => format!("android:{name}"),
+        uri => match namespaces
+            .iter()
+            .rev()
+            .find(|(declared, _)| declared == uri)
+        {
+            Some((_, prefix)) => format!("{prefix}:{name}"),
+            None => name,
+        },
+    };
+    Ok(match compiled {
+        Some(Compiled::Reference(id)) => (name, format!("@0x{id:08x}"), Some(ValueType::Reference)),
+        Some(Compiled::Value(compiled, value_type)) if value.is_empty() => {
+            (name, compiled, Some(value_type))
+        }
+        Some(Compiled::Value(_, value_type)) => (name, value, Some(value_type)),
+        None => (name, value, None),
+    })
+}
+
+/// Decodes an `Item`, `None` for the kinds without a string rendering (e.g. dimensions)
+fn item(fields: Fields) -> Result<Option<Compiled>, XmlError> {
+    let mut compiled = None;
+    for field in fields {
+        match field? {
+            // Reference
+            (1, Field::Bytes(reference)) => {
+                for field in reference {
+                    if let (2, Field::Varint(id @ 1..)) = field? {
+                        compiled = Some(Compiled::Reference(id as u32));
+                    }
+                }
+            }
+            // String and RawString
+            (2 | 3, Field::Bytes(string)) => {
+                for field in string {
+                    if let (1, Field::Bytes(value)) = field? {
+                        compiled = Some(Compiled::Value(value.string()?, ValueType::String));
+                    }
+                }
+            }
+            // Primitive
+            (7, Field::Bytes(primitive)) => {
+                for field in primitive {
+                    let (value, value_type) = match field? {
+                        (3, Field::Fixed32(bits)) => {
+                            (f32::from_bits(bits).to_string(), ValueType::Other)
+                        }
+                        (6, Field::Varint(int)) => ((int as i32).to_string(), ValueType::Integer),
+                        (7, Field::Varint(int)) => {
+                            (format!("0x{:x}", int as u32), ValueType::Integer)
+                        }
+                        (8, Field::Varint(boolean)) => {
+                            ((boolean != 0).to_string(), ValueType::Boolean)
+                        }
+                        (9..=12, Field::Varint(color)) => {
+                            (format!("#{:08x}", color as u32), ValueType::Other)
+                        }
+                        _ => continue,
+                    };
+                    compiled = Some(Compiled::Value(value, value_type));
+                }
+            }
+            _ => {}
+        }
+    }
+    Ok(compiled)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::parse;
+    use crate::xml::{errors::XmlError, MAX_DEPTH};
+
+    #[test]
+    fn test_parse() {
+        // <manifest xmlns:a="urn:a" package="p" a:x="1"><uses-sdk/>text</manifest>
+        let buf = b"\x0a\x4a\x0a\x0a\x0a\x01a\x12\x05urn:a\x1a\x08manifest\
+            \x22\x0c\x12\x07package\x1a\x01p\
+            \x22\x0e\x0a\x05urn:a\x12\x01x\x32\x02\x3a\x00\
+            \x2a\x0c\x0a\x0a\x1a\x08uses-sdk\x2a\x06\x12\x04text";
+        let root = parse(buf).unwrap().unwrap();
+        assert_eq!(root.tag, "manifest");
+        assert_eq!(root.attributes["package"], "p");
+        // A compiled value without anything to render keeps the source value
+        assert_eq!(root.attributes["a:x"], "");
+        assert_eq!(root.children[0].tag, "uses-sdk");
+        assert_eq!(root.text, "text");
+
+        assert!(matches!(
+            parse(b"\x0a\x10\x1a\x08manifest"),
+            Err(XmlError::Proto { offset: 2, .. })
+        ));
+    }
+
+    #[test]
+    fn test_depth() {
+        fn bytes(field: u8, value: &[u8]) -> Vec<u8> {
+            let mut buf = vec![field];
+            let mut len = value.len();
+            while len >= 0x80 {
+                buf.push(len as u8 | 0x80);
+                len >>= 7;
+            }
+            buf.push(len as u8);
+            buf.extend(value);
+            buf
+        }
+        // `depth` nested `XmlNode`s of `<a>` elements
+        let nested = |depth: usize| {
+            (0..depth).fold(Vec::new(), |child, _| {
+                let mut element = bytes(0x1a, b"a");
+                if !child.is_empty() {
+                    element.extend(bytes(0x2a, &child));
+                }
+                bytes(0x0a, &element)
+            })
+        };
+        assert!(parse(&nested(MAX_DEPTH)).is_ok());
+        assert!(matches!(
+            parse(&nested(MAX_DEPTH + 1)),
+            Err(XmlError::Proto { .. })
+        ));
+    }
+}
@@ -0,0 +1,314 @@
+//! Minimal parser of plain-text XML (e.g. source or apktool decoded manifests).
+//!
+//! Namespace prefixes are normalized to the ones binary XML uses (`android:`),
+//! declarations, comments and processing instructions are skipped and DTDs are not supported.
+
+use std::collections::HashMap;
+
+use super::{errors::XmlError, RawElement, ValueType, ANDROID_NAMESPACE, MAX_DEPTH};
+
+struct Parser<'a> {
+    input: &'a str,
+    offset: usize,
+    /// Namespace URI of each prefix, one scope per open element
+    namespaces: Vec<HashMap<String, String>>,
+}
+
+fn is_name_char(c: char) -> bool {
+    c.is_alphanumeric() || matches!(c, ':' | '_' | '-' | '.')
+}
+
+impl<'a> Parser<'a> {
+    fn error(&self, message: &str) -> XmlError {
+        XmlError::Text {
+            offset: self.offset,
+            message: message.to_string(),
+        }
+    }
+
+    fn rest(&self) -> &'a str {
+        &self.input[self.offset..]
+    }
+
+    fn skip_whitespace(&mut self) {
+        let rest = self.rest();
+        self.offset += rest.len() - rest.trim_start().len();
+    }
+
+    /// Skips past the next occurrence of `end`
+    fn skip_past(&mut self, end: &str) -> Result<&'a str, XmlError> {
+        let rest = self.rest();
+        match rest.find(end) {
+            Some(i) => {
+                self.offset += i + end.len();
+                Ok(&rest[..i])
+            }
+            None => Err(self.error(&format!("Missing {end}"))),
+        }
+    }
+
+    fn expect(&mut self, token: &str) -> Result<(), XmlError> {
+        if self.rest().starts_with(token) {
+            self.offset += token.len();
+            Ok(())
+        } else {
+            Err(self.error(&format!("Expected {token}")))
+        }
+    }
+
+    fn name(&mut self) -> Result<&'a str, XmlError> {
+        let rest = self.rest();
+        let len = rest.find(|c| !is_name_char(c)).unwrap_or(rest.len());
+        if len == 0 {
+            return Err(self.error("Expected a name"));
+        }
+        self.offset += len;
+        Ok(&rest[..len])
+    }
+
+    /// Skips the prolog, comments, processing instructions and doctypes
+    fn skip_misc(&mut self) -> Result<(), XmlError> {
+        loop {
+            self.skip_whitespace();
+            if self.rest().starts_with("<?") {
+                self.skip_past("?>")?;
+            } else if self.rest().starts_with("<!--") {
+                self.skip_past("-->")?;
+            } else if self.rest().starts_with("<!DOCTYPE") {
+                self.skip_past(">")?;
+            } else {
+                return Ok(());
+            }
+        }
+    }
+
+    /// Replaces the prefix of an attribute name by the one binary XML uses for its namespace,
+    /// the innermost declaration of the prefix wins
+    fn normalize(&self, name: &str) -> String {
+        match name.split_once(':') {
+            Some((prefix, local)) => {
+                let uri = self
+                    .namespaces
+                    .iter()
+                    .rev()
+                    .find_map(|scope| scope.get(prefix))
+                    .map(String::as_str);
+                match uri {
+                    Some(ANDROID_NAMESPACE) => format!("android:{local}"),
+                    _ => name.to_string(),
+                }
+            }
+            None => name.to_string(),
+        }
+    }
+
+    fn element(&mut self) -> Result<RawElement, XmlError> {
+        if self.namespaces.len() == MAX_DEPTH {
+            return Err(self.error(&format!("Elements nested deeper than {MAX_DEPTH}")));
+        }
+        self.expect("<")?;
+        let tag = self.name()?.to_string();
+        let mut attributes = Vec::new();
+        let mut declarations = HashMap::new();
+        let empty = loop {
+            self.skip_whitespace();
+            if self.rest().starts_with("/>") {
+                self.offset += 2;
+                break true;
+            }
+            if self.rest().starts_with('>') {
+                self.offset += 1;
+                break false;
+            }
+            let name = self.name()?;
+            self.skip_whitespace();
+            self.expect("=")?;
+            self.skip_whitespace();
+            let quote = match self.rest().chars().next() {
+                Some(q @ ('"' | '\'')) => q,
+                _ => return Err(self.error("Expected a quoted value")),
+            };
+            self.offset += 1;
+            let value = unescape(self.skip_past(&quote.to_string())?);
+            match name.strip_prefix("xmlns:") {
+                Some(prefix) => {
+                    declarations.insert(prefix.to_string(), value);
+                }
+                None if name == "xmlns" => {}
+                None => attributes.push((name, value)),
+            }
+        };
+
+        // The declarations of the element apply to its own attributes and to its descendants
+        self.namespaces.push(declarations);
+        let attributes: HashMap<_, _> = attributes
+            .into_iter()
+            .map(|(name, value)| (self.normalize(name), value))
+            .collect();
+        let mut raw = RawElement {
+            tag,
+            types: attributes
+                .iter()
+                .map(|(name, value)| (name.clone(), ValueType::infer(value)))
+                .collect(),
+            attributes,
+            ..Default::default()
+        };
+        if !empty {
+            self.content(&mut raw)?;
+        }
+        self.namespaces.pop();
+        Ok(raw)
+    }
+
+    /// Parses the children and text of an element up to its end tag
+    fn content(&mut self, raw: &mut RawElement) -> Result<(), XmlError> {
+        loop {
+            let rest = self.rest();
+            if rest.starts_with("</") {
+                self.offset += 2;
+                let end = self.name()?;
+                if end != raw.tag {
+                    return Err(self.error(&format!("Expected </{}>", raw.tag)));
+                }
+                self.skip_whitespace();
+                return self.expect(">");
+            } else if rest.starts_with("<!--") {
+                self.skip_past("-->")?;
+            } else if rest.starts_with("<![CDATA[") {
+                self.offset += 9;
+                raw.text.push_str(self.skip_past("]]>")?);
+            } else if rest.starts_with("<?") {
+                self.skip_past("?>")?;
+            } else if rest.starts_with('<') {
+                raw.children.push(self.element()?);
+            } else if rest.is_empty() {
+                return Err(self.error(&format!("Missing </{}>", raw.tag)));
+            } else {
+                let len = rest.find('<').unwrap_or(rest.len());
+                raw.text.push_str(unescape(&rest[..len]).trim());
+                self.offset += len;
+            }
+        }
+    }
+}
+
+/// Replaces the predefined entities and character references
+fn unescape(value: &str) -> String {
+    let mut result = String::with_capacity(value.len());
+    let mut rest = value;
+    while let Some(start) = rest.find('&') {
+        result.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let Some(end) = rest.find(';') else {
+            break;
+        };
+        let entity = &rest[1..end];
+        let decoded = match entity {
+            "amp" => Some('&'),
+            "lt" => Some('<'),
+            "gt" => Some('>'),
+            "quot" => Some('"'),
+            "apos" => Some('\''),
+            _ => entity
+                .strip_prefix("#x")
+                .map(|hex| u32::from_str_radix(hex, 16))
+                .or_else(|| entity.strip_prefix('#').map(str::parse))
+                .and_then(Result::ok)
+                .and_then(char::from_u32),
+        };
+        match decoded {
+            Some(c) => {
+                result.push(c);
+                rest = &rest[end + 1..];
+            }
+            None => {
+                result.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    result.push_str(rest);
+    result
+}
+
+/// Parses a text XML document into its root element
+pub(crate) fn parse(input: &str) -> Result<RawElement, XmlError> {
+    let mut parser = Parser {
+        input: input.trim_start_matches('\u{feff}'),
+        offset: 0,
+        namespaces: Vec::new(),
+    };
+    parser.skip_misc()?;
+    let root = parser.element()?;
+    parser.skip_misc()?;
+    if !parser.rest().is_empty() {
+        return Err(parser.error("Content after the root element"));
+    }
+    Ok(root)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse, unescape};
+    use crate::xml::MAX_DEPTH;
+
+    #[test]
+    fn test_parse() {
+        let root = parse(
+            r#"<?xml version="1.0" encoding="utf-8"?>
+            <!-- comment -->
+            <manifest xmlns:a="http://schemas.android.com/apk/res/android"
+                xmlns:tools="http://schemas.android.com/tools" package='com.example'>
+                <application a:label="Tom &amp; Jerry" tools:ignore="x"/>
+                <pin digest="SHA-256"> abc= <!-- c --></pin>
+                <data><![CDATA[<raw>]]></data>
+            </manifest>"#,
+        )
+        .unwrap();
+        assert_eq!(root.tag, "manifest");
+        assert_eq!(root.attributes.len(), 1);
+        assert_eq!(root.attributes["package"], "com.example");
+        assert_eq!(root.children[0].attributes["android:label"], "Tom & Jerry");
+        assert_eq!(root.children[0].attributes["tools:ignore"], "x");
+        assert_eq!(root.children[1].text, "abc=");
+        assert_eq!(root.children[2].text, "<raw>");
+
+        // Declarations are scoped to their element
+        let root = parse(
+            r#"<manifest xmlns:a="http://schemas.android.com/apk/res/android" a:x="1">
+                <child xmlns:a="urn:other" a:y="2">
+                    <grandchild a:z="3"/>
+                </child>
+                <sibling xmlns:b="http://schemas.android.com/apk/res/android" b:w="4"/>
+                <other a:v="5" b:u="6"/>
+            </manifest>"#,
+        )
+        .unwrap();
+        assert!(root.attributes.contains_key("android:x"));
+        assert!(root.children[0].attributes.contains_key("a:y"));
+        assert!(root.children[0].children[0].attributes.contains_key("a:z"));
+        assert!(root.children[1].attributes.contains_key("android:w"));
+        assert!(root.children[2].attributes.contains_key("android:v"));
+        assert!(root.children[2].attributes.contains_key("b:u"));
+
+        assert!(parse("<a><b></a>").is_err());
+        assert!(parse("<a x=1/>").is_err());
+        assert!(parse("<a/><b/>").is_err());
+    }
+
+    #[test]
+    fn test_unescape() {
+        assert_eq!(
+            unescape("&lt;&#65;&#x42;&gt; &unknown; &"),
+            "<AB> &unknown; &"
+        );
+    }
+
+    #[test]
+    fn test_depth() {
+        let nested = |depth: usize| "<a>".repeat(depth) + &"</a>".repeat(depth);
+        assert!(parse(&nested(MAX_DEPTH)).is_ok());
+        assert!(parse(&nested(MAX_DEPTH + 1)).is_err());
+    }
+}